@@ -1,17 +1,15 @@
-use std::{fmt, str::FromStr};
 use clap::{Parser, ValueEnum};
+use std::{fmt, str::FromStr};
 
 use crate::verify_file;
 
 #[derive(Debug, Parser)]
 pub enum Base64SubCommand {
-
     #[command(name = "encode", about = "Encode a string to base64")]
     Encode(Base64EncodeOpts),
 
     #[command(name = "decode", about = "Decode a base64 string")]
     Decode(Base64DecodeOpts),
-
 }
 
 #[derive(Debug, Parser)]
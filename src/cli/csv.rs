@@ -1,35 +1,530 @@
-use clap::{Parser, ValueEnum};
-use std::{fmt::Display, str::FromStr};
+use clap::{builder::ArgPredicate, ArgAction, Args, Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, path::PathBuf, str::FromStr};
 
-use crate::verify_file;
+use crate::{verify_file, verify_path};
 
 ///
 /// 读取输入的csv文件内容，将内容转换为json格式，并输出到指定文件
+/// 指定子命令时（如 stats）执行对应的操作
 ///
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpt {
-    /// 输入文件路径
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+    /// 输入文件路径，- 表示标准输入
     #[arg(short, long, required = true, value_parser = verify_file)]
-    pub input: String,
-    /// 输出文件路径
+    pub input: Option<String>,
+    /// 输出文件路径，- 表示标准输出，默认为 output.<format>
     // #[arg(short, long, default_value = "output.json")]
     // pub output: String,
     #[arg(short, long)]
     pub output: Option<String>,
+    /// 输入格式，非 csv 时将 json/yaml/ndjson 转换为 csv（忽略 --format）
+    #[arg(long, value_parser = parse_input_format, default_value = "csv")]
+    pub from: InputFormat,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[command(flatten)]
+    pub convert: CsvConvertOpts,
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+#[derive(Debug, Parser)]
+pub enum CsvSubCommand {
+    #[command(about = "Show per-column statistics of a CSV file")]
+    Stats(CsvStatsOpts),
+    #[command(about = "Show a CSV file as an aligned table in the terminal")]
+    Show(CsvShowOpts),
+    #[command(about = "Sort and de-duplicate CSV rows, spilling to disk for large files")]
+    Sort(CsvSortOpts),
+    #[command(about = "Join two CSV files on key columns")]
+    Join(CsvJoinOpts),
+    #[command(about = "Diff two CSV files by primary key columns")]
+    Diff(CsvDiffOpts),
+    #[command(about = "Aggregate CSV columns grouped by key columns")]
+    Agg(CsvAggOpts),
+    #[command(about = "Run a SQL query over one or more CSV files")]
+    Query(CsvQueryOpts),
+    #[command(about = "Validate a CSV file against a YAML spec or JSON Schema")]
+    Validate(CsvValidateOpts),
+    #[command(about = "Split a CSV file into multiple files by row count or column value")]
+    Split(CsvSplitOpts),
+    #[command(about = "Concatenate CSV files, reconciling their headers by column name")]
+    Cat(CsvCatOpts),
+    #[command(about = "Randomly sample CSV rows in one pass, optionally per group")]
+    Sample(CsvSampleOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvStatsOpts {
+    /// 输入文件路径
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    /// 以 JSON 格式输出统计结果，默认输出表格
+    #[arg(long)]
+    pub json: bool,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvShowOpts {
+    /// 输入文件路径
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    /// 只显示前 N 行
+    #[arg(long)]
+    pub head: Option<usize>,
+    /// 只显示后 N 行
+    #[arg(long)]
+    pub tail: Option<usize>,
+    /// 单元格最大显示宽度，超出部分截断
+    #[arg(long, default_value = "40")]
+    pub max_width: usize,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSortOpts {
+    /// 输入文件路径，- 表示标准输入
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    /// 输出文件路径，- 表示标准输出
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// 排序列，格式为 列名[:asc|desc][:auto|num|lex|date]，如 --by "Read Count:desc,Publish Time"
+    #[arg(long, value_parser = parse_sort_key, value_delimiter = ',', required_unless_present = "dedup_by")]
+    pub by: Vec<SortKey>,
+    /// 按指定列去重，保留排序后第一次出现的行
+    #[arg(long, value_delimiter = ',')]
+    pub dedup_by: Vec<String>,
+    /// 排序使用的内存上限，超出后将已排序的数据块写入临时文件，如 64M, 1G
+    #[arg(long, value_parser = parse_size, default_value = "256M")]
+    pub memory_limit: usize,
+    /// 临时文件目录，默认为系统临时目录
+    #[arg(long, value_parser = verify_path)]
+    pub temp_dir: Option<PathBuf>,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvJoinOpts {
+    /// 左表文件路径
+    #[arg(value_parser = verify_file)]
+    pub left: String,
+    /// 右表文件路径
+    #[arg(value_parser = verify_file)]
+    pub right: String,
+    /// 两表中同名的连接列，多列用逗号分隔
+    #[arg(long, value_delimiter = ',', required_unless_present = "left_on", conflicts_with_all = ["left_on", "right_on"])]
+    pub on: Vec<String>,
+    /// 左表的连接列（列名不同时使用），与 --right-on 一一对应
+    #[arg(long, value_delimiter = ',', requires = "right_on")]
+    pub left_on: Vec<String>,
+    /// 右表的连接列
+    #[arg(long, value_delimiter = ',', requires = "left_on")]
+    pub right_on: Vec<String>,
+    /// 连接方式：inner, left, right, full
+    #[arg(long, value_parser = parse_join_type, default_value = "inner")]
+    pub how: JoinType,
+    /// 输出文件路径，- 表示标准输出
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// 右表在内存中建立哈希表的内存上限，超出后将两表按键分区写入临时文件再逐个分区连接；单个键的行过多时可能超出该上限
+    #[arg(long, value_parser = parse_size, default_value = "256M")]
+    pub memory_limit: usize,
+    /// 临时文件目录，默认为系统临时目录
+    #[arg(long, value_parser = verify_path)]
+    pub temp_dir: Option<PathBuf>,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvDiffOpts {
+    /// 旧文件路径
+    #[arg(value_parser = verify_file)]
+    pub old: String,
+    /// 新文件路径
+    #[arg(value_parser = verify_file)]
+    pub new: String,
+    /// 主键列，多列用逗号分隔，两个文件中的主键必须唯一
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub key: Vec<String>,
+    /// 以 JSON patch 风格的操作列表输出，默认输出文本摘要
+    #[arg(long)]
+    pub json: bool,
+    /// 存在差异时以状态码 1 退出，便于在 CI 中使用
+    #[arg(long)]
+    pub exit_code: bool,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvValidateOpts {
+    /// 输入文件路径，- 表示标准输入
+    #[arg(value_parser = verify_file)]
+    pub input: String,
+    /// 校验规则文件：YAML 格式的列规则，或 JSON Schema（properties/required）
+    #[arg(long, value_parser = verify_file)]
+    pub schema: String,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSplitOpts {
+    /// 输入文件路径，- 表示标准输入
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    /// 每个文件最多包含的行数（不含表头）
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), required_unless_present = "by", conflicts_with = "by")]
+    pub rows: Option<u64>,
+    /// 按该列的取值拆分，每个取值输出一个文件
+    #[arg(long)]
+    pub by: Option<String>,
+    /// 输出目录
+    #[arg(long, value_parser = verify_path, default_value = ".")]
+    pub out_dir: PathBuf,
+    /// 输出文件名前缀，默认为输入文件名（标准输入时为 part）
+    #[arg(long)]
+    pub prefix: Option<String>,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvCatOpts {
+    /// 输入文件路径，按顺序拼接
+    #[arg(value_parser = verify_file, required = true)]
+    pub inputs: Vec<String>,
+    /// 输出文件路径，- 表示标准输出
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSampleOpts {
+    /// 输入文件路径，- 表示标准输入
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    /// 输出文件路径，- 表示标准输出
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// 抽样行数；指定 --stratify 时为每组的行数
+    #[arg(short = 'n', long = "rows")]
+    pub rows: usize,
+    /// 随机数种子，相同的种子和输入得到相同的结果
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// 分层抽样：按该列的取值分组，每组分别抽样
+    #[arg(long)]
+    pub stratify: Option<String>,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvAggOpts {
+    /// 输入文件路径，- 表示标准输入
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+    /// 输出文件路径，- 表示标准输出
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// 分组列，多列用逗号分隔；不指定时对整个文件聚合
+    #[arg(long, value_delimiter = ',')]
+    pub group_by: Vec<String>,
+    /// 求和的列
+    #[arg(long, value_delimiter = ',')]
+    pub sum: Vec<String>,
+    /// 求平均值的列
+    #[arg(long, value_delimiter = ',')]
+    pub avg: Vec<String>,
+    /// 求最小值的列
+    #[arg(long, value_delimiter = ',')]
+    pub min: Vec<String>,
+    /// 求最大值的列
+    #[arg(long, value_delimiter = ',')]
+    pub max: Vec<String>,
+    /// 输出每组的行数，未指定任何聚合时默认输出
+    #[arg(long)]
+    pub count: bool,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvQueryOpts {
+    /// SQL 查询，如 "SELECT Title FROM zcyj WHERE Position = 1 LIMIT 10"
+    pub query: String,
+    /// 表名与文件的对应关系，如 --table zcyj=data/zcyj.csv；未指定时使用 <表名> 或 <表名>.csv
+    #[arg(short, long = "table", value_parser = parse_table)]
+    pub tables: Vec<(String, String)>,
+    /// 输出文件路径，- 表示标准输出
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+///
+/// 读取csv文件时的公共选项
+///
+#[derive(Debug, Clone, Args)]
+pub struct CsvReadOpts {
+    /// 分隔符，单个ASCII字符，制表符可写作 '\t'
+    #[arg(short, long, value_parser = parse_delimiter, default_value = ",")]
+    pub delimiter: u8,
+    /// 是否包含表头，--header false 表示首行即数据
+    #[arg(long, action = ArgAction::Set, default_value = "true")]
+    pub header: bool,
+    /// 自定义列名，如 --columns a,b,c；未提供且无表头时使用 col1, col2, ...
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Option<Vec<String>>,
+    /// 输入文件编码，如 utf-8, gbk, gb18030, utf-16le；auto 根据 BOM 和内容自动识别：
+    /// 只区分 UTF-8、UTF-16 和 GB18030，不是 UTF-8/UTF-16 的输入一律按 GB18030（兼容 GBK）解码，
+    /// 其他编码（如 Big5、Shift_JIS）需要显式指定
+    #[arg(long, value_parser = parse_encoding, default_value = "auto")]
+    pub encoding: String,
+    /// 允许各行字段数不同，缺少的字段输出为 null，多余的字段忽略
+    #[arg(long)]
+    pub flexible: bool,
+    /// 遇到无法解析的行时：fail 终止，skip 跳过并统计，report 跳过并输出行号和错误；
+    /// 指定 --error-report 时默认为 report
+    #[arg(
+        long,
+        value_parser = parse_on_error,
+        default_value = "fail",
+        default_value_if("error_report", ArgPredicate::IsPresent, "report")
+    )]
+    pub on_error: OnError,
+    /// 错误报告写入的文件，指定后隐含 --on-error report；未指定时 report 输出到标准错误
+    #[arg(long)]
+    pub error_report: Option<String>,
+}
+
+impl Default for CsvReadOpts {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            columns: None,
+            encoding: "auto".to_string(),
+            flexible: false,
+            on_error: OnError::Fail,
+            error_report: None,
+        }
+    }
+}
+
+///
+/// 将csv记录转换为 JSON/YAML 值时的选项
+///
+#[derive(Debug, Clone, Default, Args)]
+pub struct CsvConvertOpts {
+    /// 自动推断单元格类型：整数、浮点数、布尔值转为对应类型，空单元格转为 null
+    #[arg(long)]
+    pub infer: bool,
+    /// 指定列类型，如 --types "Read Count=int,Publish Time=datetime"；
+    /// datetime 列与省略格式的 --datetime 相同，按 --tz 和 --datetime-output 输出
+    #[arg(long, value_parser = parse_column_type, value_delimiter = ',')]
+    pub types: Vec<(String, ColumnType)>,
+    /// 只输出指定的列（列名或从 1 开始的序号），并按给定顺序排列，如 --select Title,3
+    #[arg(long, value_delimiter = ',')]
+    pub select: Vec<String>,
+    /// 排除指定的列（列名或从 1 开始的序号），如 --exclude Link
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+    /// 重命名输出列，如 --rename "Read Count=reads"
+    #[arg(long, value_parser = parse_rename, value_delimiter = ',')]
+    pub rename: Vec<(String, String)>,
+    /// 只输出满足条件的行（使用输出列名），如 --where '"Read Count" > 50000 && Position == 1'；
+    /// 脱敏列按脱敏前的值比较
+    #[arg(long = "where")]
+    pub filter: Option<String>,
+    /// 按列名还原嵌套结构：a.b 转为嵌套对象，a[0] 转为数组（不支持 csv/md 输出）
+    #[arg(long)]
+    pub unflatten: bool,
+    /// 脱敏指定的列：hash（带密钥的 BLAKE3）、partial[:前缀长度:后缀长度]、redact，
+    /// 如 --mask "Email=hash,Phone=partial:3:4,Name=redact"
+    #[arg(long, value_parser = parse_mask, value_delimiter = ',')]
+    pub mask: Vec<(String, MaskRule)>,
+    /// hash 脱敏使用的密钥文件，格式与 text sign/verify 的 blake3 密钥相同
+    #[arg(long, value_parser = verify_file)]
+    pub mask_key: Option<String>,
+    /// 按 strftime 格式解析日期时间列，可重复指定，如 --datetime "DOB=%b %d, %Y"；
+    /// 省略格式时自动识别常见格式，值后面以空白分隔的附加内容会被忽略
+    #[arg(long, value_parser = parse_datetime_spec)]
+    pub datetime: Vec<(String, Option<String>)>,
+    /// 不带时区的日期时间所在的时区，也是 RFC 3339 输出使用的时区：utc, local 或 +08:00
+    #[arg(long, value_parser = parse_datetime_zone, default_value = "utc")]
+    pub tz: DatetimeZone,
+    /// --datetime 列和 datetime 类型列的输出形式：rfc3339 或 epoch（Unix 时间戳，秒）
+    #[arg(long, value_parser = parse_datetime_output, default_value = "rfc3339")]
+    pub datetime_output: DatetimeOutput,
+}
+
+///
+/// 输出格式相关的选项
+///
+#[derive(Debug, Clone, Args)]
+pub struct CsvWriteOpts {
+    /// 输出格式：json, yaml, toml, ndjson, csv, md
     #[arg(long, value_parser = parse_format, default_value = "json")]
     pub format: OutputFormat,
-    /// 分隔符
-    #[arg(short, long, default_value = ",")]
-    pub delimiter: char,
-    /// 是否包含表头
-    #[arg(long, default_value = "true")]
-    pub header: bool,
+    /// 输出为csv时使用的分隔符
+    #[arg(long, value_parser = parse_delimiter, default_value = ",")]
+    pub out_delimiter: u8,
+    /// 输出为csv时的引号策略：necessary, always, never, non-numeric
+    #[arg(long, value_parser = parse_quote_style, default_value = "necessary")]
+    pub quote: QuoteStyle,
+}
+
+impl Default for CsvWriteOpts {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Json,
+            out_delimiter: b',',
+            quote: QuoteStyle::Necessary,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    String,
+    Int,
+    Float,
+    Bool,
+    Datetime,
+}
+
+fn parse_encoding(encoding: &str) -> Result<String, anyhow::Error> {
+    let encoding = encoding.trim().to_ascii_lowercase();
+    if encoding == "auto" || encoding_rs::Encoding::for_label(encoding.as_bytes()).is_some() {
+        Ok(encoding)
+    } else {
+        Err(anyhow::anyhow!("Unsupported encoding: {}", encoding))
+    }
+}
+
+fn parse_delimiter(delimiter: &str) -> Result<u8, anyhow::Error> {
+    match delimiter {
+        "\\t" | "tab" => Ok(b'\t'),
+        s if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        why => Err(anyhow::anyhow!(
+            "Delimiter must be a single ASCII character: {}",
+            why
+        )),
+    }
 }
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum OutputFormat {
     Json,
     Yaml,
+    Toml,
+    Ndjson,
+    Csv,
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    Csv,
+    Json,
+    Yaml,
+    Ndjson,
+}
+
+///
+/// 日期时间所在的时区
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatetimeZone {
+    #[default]
+    Utc,
+    Local,
+    Fixed(chrono::FixedOffset),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatetimeOutput {
+    #[default]
+    Rfc3339,
+    Epoch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnError {
+    Fail,
+    Skip,
+    Report,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKind {
+    Auto,
+    Numeric,
+    Lexical,
+    Date,
+}
+
+///
+/// 一个排序列：列名（或从 1 开始的序号）、是否降序以及比较方式
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub desc: bool,
+    pub kind: SortKind,
+}
+
+///
+/// 列的脱敏方式
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskRule {
+    /// 带密钥的 BLAKE3 哈希，相同的值在不同文件中得到相同的结果，便于关联
+    Hash,
+    /// 只保留前 prefix 个和后 suffix 个字符，其余替换为 '*'
+    Partial { prefix: usize, suffix: usize },
+    /// 整体替换为固定的标记
+    Redact,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum QuoteStyle {
+    Necessary,
+    Always,
+    Never,
+    NonNumeric,
 }
 
 fn parse_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
@@ -48,6 +543,10 @@ impl From<OutputFormat> for &'static str {
         match format {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::Toml => "toml",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Markdown => "md",
         }
     }
 }
@@ -57,8 +556,408 @@ impl FromStr for OutputFormat {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "json" => Ok(OutputFormat::Json),
-            "yaml" => Ok(OutputFormat::Yaml),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "toml" => Ok(OutputFormat::Toml),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "md" | "markdown" => Ok(OutputFormat::Markdown),
             why => Err(anyhow::anyhow!("Unsupported format: {}", why)),
         }
     }
 }
+
+fn parse_input_format(format: &str) -> Result<InputFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<InputFormat> for &'static str {
+    fn from(format: InputFormat) -> Self {
+        match format {
+            InputFormat::Csv => "csv",
+            InputFormat::Json => "json",
+            InputFormat::Yaml => "yaml",
+            InputFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(InputFormat::Csv),
+            "json" => Ok(InputFormat::Json),
+            "yaml" | "yml" => Ok(InputFormat::Yaml),
+            "ndjson" | "jsonl" => Ok(InputFormat::Ndjson),
+            why => Err(anyhow::anyhow!("Unsupported input format: {}", why)),
+        }
+    }
+}
+
+fn parse_on_error(on_error: &str) -> Result<OnError, anyhow::Error> {
+    on_error.parse()
+}
+
+impl Display for OnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<OnError> for &'static str {
+    fn from(on_error: OnError) -> Self {
+        match on_error {
+            OnError::Fail => "fail",
+            OnError::Skip => "skip",
+            OnError::Report => "report",
+        }
+    }
+}
+
+impl FromStr for OnError {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(OnError::Fail),
+            "skip" => Ok(OnError::Skip),
+            "report" => Ok(OnError::Report),
+            why => Err(anyhow::anyhow!("Unsupported on-error mode: {}", why)),
+        }
+    }
+}
+
+fn parse_join_type(how: &str) -> Result<JoinType, anyhow::Error> {
+    how.parse()
+}
+
+impl Display for JoinType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<JoinType> for &'static str {
+    fn from(how: JoinType) -> Self {
+        match how {
+            JoinType::Inner => "inner",
+            JoinType::Left => "left",
+            JoinType::Right => "right",
+            JoinType::Full => "full",
+        }
+    }
+}
+
+impl FromStr for JoinType {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inner" => Ok(JoinType::Inner),
+            "left" => Ok(JoinType::Left),
+            "right" => Ok(JoinType::Right),
+            "full" | "outer" => Ok(JoinType::Full),
+            why => Err(anyhow::anyhow!("Unsupported join type: {}", why)),
+        }
+    }
+}
+
+fn parse_sort_key(spec: &str) -> Result<SortKey, anyhow::Error> {
+    let mut column = spec.trim();
+    let mut desc = false;
+    let mut kind = SortKind::Auto;
+    // 从右向左依次识别比较方式和排序方向，列名本身可以包含 ':'
+    if let Some((rest, suffix)) = column.rsplit_once(':') {
+        if let Ok(k) = suffix.trim().parse::<SortKind>() {
+            kind = k;
+            column = rest;
+        }
+    }
+    if let Some((rest, suffix)) = column.rsplit_once(':') {
+        match suffix.trim() {
+            "asc" => column = rest,
+            "desc" => {
+                desc = true;
+                column = rest;
+            }
+            _ => {}
+        }
+    }
+    let column = column.trim();
+    if column.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Invalid sort key, missing column: {}",
+            spec
+        ));
+    }
+    Ok(SortKey {
+        column: column.to_string(),
+        desc,
+        kind,
+    })
+}
+
+impl Display for SortKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<SortKind> for &'static str {
+    fn from(kind: SortKind) -> Self {
+        match kind {
+            SortKind::Auto => "auto",
+            SortKind::Numeric => "num",
+            SortKind::Lexical => "lex",
+            SortKind::Date => "date",
+        }
+    }
+}
+
+impl FromStr for SortKind {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(SortKind::Auto),
+            "num" | "numeric" => Ok(SortKind::Numeric),
+            "lex" | "lexical" => Ok(SortKind::Lexical),
+            "date" => Ok(SortKind::Date),
+            why => Err(anyhow::anyhow!("Unsupported sort kind: {}", why)),
+        }
+    }
+}
+
+/// 解析带单位的大小，如 512K, 64M, 1G，不带单位时为字节数
+fn parse_size(size: &str) -> Result<usize, anyhow::Error> {
+    let size = size.trim();
+    let (num, unit) = size.split_at(
+        size.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(size.len()),
+    );
+    let unit = match unit.trim().to_ascii_uppercase().trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(anyhow::anyhow!("Invalid size: {}", size)),
+    };
+    let num: usize = num
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size: {}", size))?;
+    num.checked_mul(unit)
+        .ok_or_else(|| anyhow::anyhow!("Size is too large: {}", size))
+}
+
+fn parse_quote_style(quote: &str) -> Result<QuoteStyle, anyhow::Error> {
+    quote.parse()
+}
+
+impl Display for QuoteStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<QuoteStyle> for &'static str {
+    fn from(quote: QuoteStyle) -> Self {
+        match quote {
+            QuoteStyle::Necessary => "necessary",
+            QuoteStyle::Always => "always",
+            QuoteStyle::Never => "never",
+            QuoteStyle::NonNumeric => "non-numeric",
+        }
+    }
+}
+
+impl FromStr for QuoteStyle {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "necessary" => Ok(QuoteStyle::Necessary),
+            "always" => Ok(QuoteStyle::Always),
+            "never" => Ok(QuoteStyle::Never),
+            "non-numeric" => Ok(QuoteStyle::NonNumeric),
+            why => Err(anyhow::anyhow!("Unsupported quote style: {}", why)),
+        }
+    }
+}
+
+fn parse_rename(spec: &str) -> Result<(String, String), anyhow::Error> {
+    let (from, to) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid rename spec, expect old=new: {}", spec))?;
+    Ok((from.trim().to_string(), to.trim().to_string()))
+}
+
+fn parse_table(spec: &str) -> Result<(String, String), anyhow::Error> {
+    let (name, path) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid table spec, expect name=path: {}", spec))?;
+    let path = verify_file(path.trim()).map_err(|e| anyhow::anyhow!("{}: {}", e, path))?;
+    Ok((name.trim().to_string(), path))
+}
+
+fn parse_mask(spec: &str) -> Result<(String, MaskRule), anyhow::Error> {
+    let (name, rule) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid mask spec, expect name=rule: {}", spec))?;
+    Ok((name.trim().to_string(), rule.trim().parse()?))
+}
+
+impl Display for MaskRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaskRule::Hash => write!(f, "hash"),
+            MaskRule::Partial { prefix, suffix } => write!(f, "partial:{}:{}", prefix, suffix),
+            MaskRule::Redact => write!(f, "redact"),
+        }
+    }
+}
+
+impl FromStr for MaskRule {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        match parts.next().unwrap_or_default() {
+            "hash" if s == "hash" => Ok(MaskRule::Hash),
+            "redact" if s == "redact" => Ok(MaskRule::Redact),
+            "partial" => {
+                // 默认保留前 2 个和后 2 个字符
+                let mut len = |default: usize| -> Result<usize, anyhow::Error> {
+                    parts.next().map_or(Ok(default), |n| {
+                        n.trim()
+                            .parse()
+                            .map_err(|_| anyhow::anyhow!("Invalid partial mask length: {}", s))
+                    })
+                };
+                let prefix = len(2)?;
+                let suffix = len(2)?;
+                if parts.next().is_some() {
+                    return Err(anyhow::anyhow!(
+                        "Invalid partial mask, expect partial[:prefix[:suffix]]: {}",
+                        s
+                    ));
+                }
+                Ok(MaskRule::Partial { prefix, suffix })
+            }
+            _ => Err(anyhow::anyhow!("Unsupported mask rule: {}", s)),
+        }
+    }
+}
+
+fn parse_datetime_spec(spec: &str) -> Result<(String, Option<String>), anyhow::Error> {
+    let (name, format) = match spec.split_once('=') {
+        Some((name, format)) => (name.trim(), Some(format.to_string())),
+        None => (spec.trim(), None),
+    };
+    if name.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Invalid datetime spec, expect name[=format]: {}",
+            spec
+        ));
+    }
+    Ok((name.to_string(), format))
+}
+
+fn parse_datetime_zone(tz: &str) -> Result<DatetimeZone, anyhow::Error> {
+    tz.parse()
+}
+
+impl Display for DatetimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatetimeZone::Utc => write!(f, "utc"),
+            DatetimeZone::Local => write!(f, "local"),
+            DatetimeZone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl FromStr for DatetimeZone {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utc" | "z" => Ok(DatetimeZone::Utc),
+            "local" => Ok(DatetimeZone::Local),
+            _ => s
+                .parse::<chrono::FixedOffset>()
+                .map(DatetimeZone::Fixed)
+                .map_err(|_| {
+                    anyhow::anyhow!("Unsupported time zone, expect utc, local or +HH:MM: {}", s)
+                }),
+        }
+    }
+}
+
+fn parse_datetime_output(output: &str) -> Result<DatetimeOutput, anyhow::Error> {
+    output.parse()
+}
+
+impl Display for DatetimeOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<DatetimeOutput> for &'static str {
+    fn from(output: DatetimeOutput) -> Self {
+        match output {
+            DatetimeOutput::Rfc3339 => "rfc3339",
+            DatetimeOutput::Epoch => "epoch",
+        }
+    }
+}
+
+impl FromStr for DatetimeOutput {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rfc3339" => Ok(DatetimeOutput::Rfc3339),
+            "epoch" => Ok(DatetimeOutput::Epoch),
+            why => Err(anyhow::anyhow!("Unsupported datetime output: {}", why)),
+        }
+    }
+}
+
+fn parse_column_type(spec: &str) -> Result<(String, ColumnType), anyhow::Error> {
+    let (name, ty) = spec
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Invalid type spec, expect name=type: {}", spec))?;
+    Ok((name.trim().to_string(), ty.trim().parse()?))
+}
+
+impl Display for ColumnType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<ColumnType> for &'static str {
+    fn from(ty: ColumnType) -> Self {
+        match ty {
+            ColumnType::String => "string",
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+            ColumnType::Bool => "bool",
+            ColumnType::Datetime => "datetime",
+        }
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "string" => Ok(ColumnType::String),
+            "int" => Ok(ColumnType::Int),
+            "float" => Ok(ColumnType::Float),
+            "bool" => Ok(ColumnType::Bool),
+            "datetime" => Ok(ColumnType::Datetime),
+            why => Err(anyhow::anyhow!("Unsupported column type: {}", why)),
+        }
+    }
+}
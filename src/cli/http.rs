@@ -1,6 +1,6 @@
-use std::path::PathBuf;
-use clap::Parser;
 use crate::verify_path;
+use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 pub enum HttpSubCommand {
@@ -16,5 +16,4 @@ pub struct HttpServeOpts {
     /// Path to the directory to serve
     #[arg(long, default_value = "8080")]
     pub port: u16,
-
-}
\ No newline at end of file
+}
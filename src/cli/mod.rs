@@ -9,7 +9,11 @@ use clap::Parser;
 
 pub use self::{
     base64::{Base64Format, Base64SubCommand},
-    csv::OutputFormat,
+    csv::{
+        ColumnType, CsvAggOpts, CsvCatOpts, CsvConvertOpts, CsvJoinOpts, CsvQueryOpts, CsvReadOpts,
+        CsvSampleOpts, CsvSplitOpts, CsvSubCommand, CsvWriteOpts, DatetimeOutput, DatetimeZone,
+        InputFormat, JoinType, MaskRule, OnError, OutputFormat, QuoteStyle, SortKey, SortKind,
+    },
     http::HttpSubCommand,
     text::{TextSignFormat, TextSubCommand},
 };
@@ -34,7 +38,7 @@ pub struct Opts {
 #[derive(Debug, Parser)]
 pub enum Subcommand {
     #[command(name = "csv", about = "Show CSV, or convert CSV to other formats")]
-    Csv(CsvOpt),
+    Csv(Box<CsvOpt>),
     #[command(name = "genpass", about = "Generate a random password")]
     Genpass(GenpassOpt),
     #[command(subcommand)]
@@ -4,7 +4,6 @@ use clap::{Parser, ValueEnum};
 
 use crate::{verify_file, verify_path};
 
-
 #[derive(Debug, Parser)]
 pub enum TextSubCommand {
     #[command(about = "Sign a message with a privated/shared key")]
@@ -13,9 +12,9 @@ pub enum TextSubCommand {
     Verify(TextVerifyOpts),
     #[command(about = "Generate a new key")]
     Generate(TextKeyGenerateOpts),
-    #[command(about = "Encrypt a message with a public key")]
+    #[command(about = "Encrypt a message with a shared key (ChaCha20-Poly1305)")]
     Encrypt(TextEncryptOpts),
-    #[command(about = "Decrypt a message with a private key")]
+    #[command(about = "Decrypt a message with a shared key (ChaCha20-Poly1305)")]
     Decrypt(TextDecryptOpts),
 }
 
@@ -62,8 +61,8 @@ pub struct TextEncryptOpts {
     /// The text message or file to encrypt
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
-    /// The key to use for encryption
-    #[arg(short, long)]
+    /// The 32-byte key file to use for encryption
+    #[arg(short, long, value_parser = verify_file)]
     pub key: String,
 }
 
@@ -72,12 +71,9 @@ pub struct TextDecryptOpts {
     /// The text message or file to decrypt
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
-    /// The key to use for decryption
-    #[arg(short, long)]
+    /// The 32-byte key file to use for decryption
+    #[arg(short, long, value_parser = verify_file)]
     pub key: String,
-    /// The nonce to use for decryption
-    #[arg(short, long)]
-    pub nonce: String,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -90,7 +86,7 @@ fn parse_format(format: &str) -> Result<TextSignFormat, anyhow::Error> {
     format.parse()
 }
 
-impl fmt::Display for TextSignFormat{
+impl fmt::Display for TextSignFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", Into::<&str>::into(*self))
     }
@@ -115,4 +111,4 @@ impl FromStr for TextSignFormat {
             why => Err(anyhow::anyhow!("Unsupported format: {}", why)),
         }
     }
-}
\ No newline at end of file
+}
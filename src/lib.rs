@@ -2,6 +2,9 @@ mod cli;
 mod process;
 mod utils;
 
-pub use cli::{Base64SubCommand, HttpSubCommand, Opts, Subcommand, TextSubCommand, TextSignFormat};
+pub use cli::{
+    Base64SubCommand, CsvSubCommand, HttpSubCommand, InputFormat, Opts, Subcommand, TextSignFormat,
+    TextSubCommand,
+};
 pub use process::*;
-pub use utils::*;
\ No newline at end of file
+pub use utils::*;
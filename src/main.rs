@@ -1,30 +1,109 @@
-use std::fs;
 use clap::Parser;
 use rcli::{
-    process_csv, 
-    process_decode, process_encode, 
-    process_gen_pass, 
-    process_http_serve,
-    process_text_keygen, process_text_sign, process_text_verify, 
-    Base64SubCommand, HttpSubCommand, Opts, Subcommand, TextSubCommand
+    get_writer, process_csv, process_csv_agg, process_csv_cat, process_csv_diff, process_csv_join,
+    process_csv_query, process_csv_sample, process_csv_show, process_csv_sort, process_csv_split,
+    process_csv_stats, process_csv_validate, process_decode, process_encode, process_gen_pass,
+    process_http_serve, process_text_decrypt, process_text_encrypt, process_text_keygen,
+    process_text_sign, process_text_verify, process_to_csv, stdout_closed, Base64SubCommand,
+    CsvSubCommand, HttpSubCommand, InputFormat, Opts, Subcommand, TextSubCommand,
 };
+use std::{fs, io::Write};
 use zxcvbn::zxcvbn;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
+    match run().await {
+        // 下游提前关闭了标准输出（如 `| head`），按正常结束处理
+        Err(_) if stdout_closed() => Ok(()),
+        result => result,
+    }
+}
+
+async fn run() -> anyhow::Result<()> {
     let opts = Opts::parse();
     match opts.cmd {
         // rcli csv -i input.csv -o output.json --header -d ','
         // 子命令csv处理
-        Subcommand::Csv(opts) => {
-            let output = if let Some(output) = opts.output {
-                output.clone()
-            } else {
-                format!("output.{}", opts.format)
-            };
-            process_csv(&opts.input, output, opts.format)?;
-        }
+        Subcommand::Csv(opts) => match opts.cmd {
+            Some(CsvSubCommand::Stats(opts)) => {
+                let stats = process_csv_stats(&opts.input, &opts.read, opts.json)?;
+                let mut stdout = get_writer("-")?;
+                stdout.write_all(stats.as_bytes())?;
+                stdout.flush()?;
+            }
+            Some(CsvSubCommand::Show(opts)) => {
+                let mut stdout = std::io::stdout().lock();
+                process_csv_show(
+                    &opts.input,
+                    &opts.read,
+                    opts.head,
+                    opts.tail,
+                    opts.max_width,
+                    &mut stdout,
+                )?;
+            }
+            Some(CsvSubCommand::Join(opts)) => process_csv_join(&opts)?,
+            Some(CsvSubCommand::Agg(opts)) => process_csv_agg(&opts)?,
+            Some(CsvSubCommand::Query(opts)) => process_csv_query(&opts)?,
+            Some(CsvSubCommand::Cat(opts)) => process_csv_cat(&opts)?,
+            Some(CsvSubCommand::Sample(opts)) => process_csv_sample(&opts)?,
+            Some(CsvSubCommand::Split(opts)) => {
+                let mut stdout = get_writer("-")?;
+                for path in process_csv_split(&opts)? {
+                    writeln!(stdout, "{}", path.display())?;
+                }
+                stdout.flush()?;
+            }
+            Some(CsvSubCommand::Diff(opts)) => {
+                let mut stdout = get_writer("-")?;
+                let changed = process_csv_diff(
+                    &opts.old,
+                    &opts.new,
+                    &opts.read,
+                    &opts.key,
+                    opts.json,
+                    &mut stdout,
+                )?;
+                if changed && opts.exit_code {
+                    std::process::exit(1);
+                }
+            }
+            Some(CsvSubCommand::Validate(opts)) => {
+                let mut stdout = get_writer("-")?;
+                let violations =
+                    process_csv_validate(&opts.input, &opts.schema, &opts.read, &mut stdout)?;
+                if violations > 0 {
+                    std::process::exit(1);
+                }
+            }
+            Some(CsvSubCommand::Sort(opts)) => {
+                process_csv_sort(
+                    &opts.input,
+                    &opts.output,
+                    &opts.read,
+                    &opts.by,
+                    &opts.dedup_by,
+                    opts.memory_limit,
+                    opts.temp_dir.as_deref(),
+                )?;
+            }
+            None => {
+                let input = opts.input.expect("input is required without subcommand");
+                if opts.from != InputFormat::Csv {
+                    // rcli csv --from json -i input.json -o output.csv
+                    let output = opts.output.unwrap_or_else(|| "output.csv".to_string());
+                    process_to_csv(&input, output, opts.from, &opts.write)?;
+                    return Ok(());
+                }
+                let output = if let Some(output) = opts.output {
+                    output.clone()
+                } else {
+                    format!("output.{}", opts.write.format)
+                };
+                process_csv(&input, output, &opts.read, &opts.convert, &opts.write)?;
+            }
+        },
         Subcommand::Genpass(opts) => {
             let password = process_gen_pass(
                 opts.length,
@@ -47,7 +126,7 @@ async fn main() -> anyhow::Result<()> {
                 let decoded = process_decode(&opts.input, opts.format)?;
                 println!("{}", decoded);
             }
-        }
+        },
         Subcommand::Text(subcmd) => match subcmd {
             TextSubCommand::Sign(opts) => {
                 let sig = process_text_sign(&opts.input, &opts.key, opts.format)?;
@@ -57,7 +136,7 @@ async fn main() -> anyhow::Result<()> {
                 let verified = process_text_verify(&opts.input, &opts.key, &opts.sig, opts.format)?;
                 println!("{}", verified);
             }
-            TextSubCommand::Generate(opts) =>   {
+            TextSubCommand::Generate(opts) => {
                 let keys = process_text_keygen(opts.format)?;
                 match opts.format {
                     rcli::TextSignFormat::Blake3 => {
@@ -67,22 +146,24 @@ async fn main() -> anyhow::Result<()> {
                     rcli::TextSignFormat::Ed22519 => {
                         let name = &opts.output;
                         fs::write(name.join("ed25519.sk"), &keys[0])?;
-                        fs::write(name.join("ed25519.pk"), &keys[0])?;
+                        fs::write(name.join("ed25519.pk"), &keys[1])?;
                     }
                 }
             }
             TextSubCommand::Encrypt(opts) => {
-                println!("Implement text encrypt: {:?}", &opts)
+                let encrypted = process_text_encrypt(&opts.input, &opts.key)?;
+                println!("{}", encrypted);
             }
             TextSubCommand::Decrypt(opts) => {
-                println!("Implement text decrypt: {:?}", &opts)
+                let decrypted = process_text_decrypt(&opts.input, &opts.key)?;
+                println!("{}", decrypted);
             }
-        }
+        },
         Subcommand::Http(opts) => match opts {
             HttpSubCommand::Serve(opts) => {
                 process_http_serve(opts.dir, opts.port).await?;
             }
-        }
+        },
     }
     Ok(())
 }
@@ -1,6 +1,8 @@
 use crate::{cli::Base64Format, get_reader};
-use base64::{engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD}, Engine as _};
-
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
 
 pub fn process_encode(input: &str, format: Base64Format) -> anyhow::Result<String> {
     let mut reader = get_reader(input)?;
@@ -19,8 +21,8 @@ pub fn process_decode(input: &str, format: Base64Format) -> anyhow::Result<Strin
     reader.read_to_string(&mut buf)?;
     let buf = buf.trim();
     let decoded = match format {
-        Base64Format::Standard => STANDARD.decode(&buf)?,
-        Base64Format::URLSafe => URL_SAFE_NO_PAD.decode(&buf)?,
+        Base64Format::Standard => STANDARD.decode(buf)?,
+        Base64Format::URLSafe => URL_SAFE_NO_PAD.decode(buf)?,
     };
     let decoded = String::from_utf8(decoded)?;
     Ok(decoded)
@@ -50,4 +52,4 @@ mod tests {
         let decoded = URL_SAFE_NO_PAD.decode(&s).unwrap();
         println!("{:?}", String::from_utf8(decoded));
     }
-}
\ No newline at end of file
+}
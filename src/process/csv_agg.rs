@@ -0,0 +1,350 @@
+use super::{
+    csv_convert::{build_reader, resolve_column},
+    csv_errors::RowErrors,
+    csv_output::row_writer,
+};
+use crate::{cli::CsvAggOpts, get_writer};
+use anyhow::Result;
+use csv::StringRecord;
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+
+///
+/// 按分组列聚合csv，一次遍历完成，内存占用与分组数成正比
+///
+/// 每组输出一行：分组列、count 以及 sum(列)/avg(列)/min(列)/max(列)，分组按首次出现的顺序输出。
+/// 空单元格不参与计算，无法解析为数值的单元格按 --on-error 处理
+///
+pub fn process_csv_agg(opts: &CsvAggOpts) -> Result<()> {
+    let (mut reader, headers) = build_reader(&opts.input, &opts.read)?;
+    let group_by = opts
+        .group_by
+        .iter()
+        .map(|spec| resolve_column(&headers, spec))
+        .collect::<Result<Vec<_>>>()?;
+    let mut aggs = Vec::new();
+    for (func, columns) in [
+        (AggFunc::Sum, &opts.sum),
+        (AggFunc::Avg, &opts.avg),
+        (AggFunc::Min, &opts.min),
+        (AggFunc::Max, &opts.max),
+    ] {
+        for spec in columns {
+            let index = resolve_column(&headers, spec)?;
+            let name = format!("{}({})", func.name(), &headers[index]);
+            // 重复指定的聚合只输出一次
+            if aggs.iter().all(|(_, _, n)| *n != name) {
+                aggs.push((func, index, name));
+            }
+        }
+    }
+    let count = opts.count || aggs.is_empty();
+
+    // 输出列名在读取数据前检查，避免分组列与 count 或聚合列重名时互相覆盖
+    let mut out_headers: Vec<String> = group_by.iter().map(|&i| headers[i].to_string()).collect();
+    if count {
+        out_headers.push("count".to_string());
+    }
+    out_headers.extend(aggs.iter().map(|(_, _, name)| name.clone()));
+    for (i, name) in out_headers.iter().enumerate() {
+        if out_headers[..i].contains(name) {
+            anyhow::bail!("output column {:?} appears more than once", name);
+        }
+    }
+
+    // 每列只需要一个累加器，不同的聚合函数共享
+    let mut columns: Vec<usize> = aggs.iter().map(|(_, index, _)| *index).collect();
+    columns.sort();
+    columns.dedup();
+
+    let mut groups: Vec<(Vec<String>, Group)> = Vec::new();
+    let mut index: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut errors = RowErrors::new(&opts.read)?;
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        let values = match parse_numbers(&record, &columns, &headers) {
+            Ok(values) => values,
+            Err(e) => {
+                errors.handle(record.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        let key: Vec<String> = group_by
+            .iter()
+            .map(|&i| record.get(i).unwrap_or_default().to_string())
+            .collect();
+        let i = *index.entry(key.clone()).or_insert_with(|| {
+            groups.push((key, Group::new(columns.len())));
+            groups.len() - 1
+        });
+        groups[i].1.update(&values);
+    }
+    errors.finish()?;
+
+    let mut writer = row_writer(get_writer(&opts.output)?, out_headers, &opts.write)?;
+    for (key, group) in &groups {
+        let mut row = Map::new();
+        for (&i, value) in group_by.iter().zip(key) {
+            row.insert(headers[i].to_string(), Value::String(value.clone()));
+        }
+        if count {
+            row.insert("count".to_string(), Value::from(group.rows));
+        }
+        for (func, index, name) in &aggs {
+            let acc = &group.columns[columns.binary_search(index).expect("column is accumulated")];
+            row.insert(name.clone(), acc.result(*func));
+        }
+        writer.write_row(&Value::Object(row))?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AggFunc {
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggFunc {
+    fn name(&self) -> &'static str {
+        match self {
+            AggFunc::Sum => "sum",
+            AggFunc::Avg => "avg",
+            AggFunc::Min => "min",
+            AggFunc::Max => "max",
+        }
+    }
+}
+
+struct Group {
+    rows: u64,
+    columns: Vec<Accumulator>,
+}
+
+impl Group {
+    fn new(columns: usize) -> Self {
+        Self {
+            rows: 0,
+            columns: (0..columns).map(|_| Accumulator::default()).collect(),
+        }
+    }
+
+    fn update(&mut self, values: &[Option<Number>]) {
+        self.rows += 1;
+        for (acc, value) in self.columns.iter_mut().zip(values) {
+            if let Some(n) = value {
+                acc.update(n);
+            }
+        }
+    }
+}
+
+///
+/// 单列的累加器；全部为整数时 sum/min/max 以整数输出
+///
+struct Accumulator {
+    count: u64,
+    sum: f64,
+    int_sum: Option<i64>,
+    min: Option<Number>,
+    max: Option<Number>,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            int_sum: Some(0),
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl Accumulator {
+    fn update(&mut self, n: &Number) {
+        let f = n.as_f64().unwrap_or_default();
+        self.count += 1;
+        self.sum += f;
+        self.int_sum = match (self.int_sum, n.as_i64()) {
+            (Some(sum), Some(i)) => sum.checked_add(i),
+            _ => None,
+        };
+        if self
+            .min
+            .as_ref()
+            .is_none_or(|m| f < m.as_f64().unwrap_or_default())
+        {
+            self.min = Some(n.clone());
+        }
+        if self
+            .max
+            .as_ref()
+            .is_none_or(|m| f > m.as_f64().unwrap_or_default())
+        {
+            self.max = Some(n.clone());
+        }
+    }
+
+    fn result(&self, func: AggFunc) -> Value {
+        if self.count == 0 {
+            // 没有数值时 sum 为 0，其余为 null
+            return match func {
+                AggFunc::Sum => Value::from(0),
+                _ => Value::Null,
+            };
+        }
+        match func {
+            AggFunc::Sum => match self.int_sum {
+                Some(sum) => Value::from(sum),
+                None => float_value(self.sum),
+            },
+            AggFunc::Avg => float_value(self.sum / self.count as f64),
+            AggFunc::Min => self.min.clone().map_or(Value::Null, Value::Number),
+            AggFunc::Max => self.max.clone().map_or(Value::Null, Value::Number),
+        }
+    }
+}
+
+fn float_value(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+/// 解析需要聚合的列，空单元格为 None
+fn parse_numbers(
+    record: &StringRecord,
+    columns: &[usize],
+    headers: &StringRecord,
+) -> Result<Vec<Option<Number>>> {
+    columns
+        .iter()
+        .map(|&i| {
+            let field = record.get(i).unwrap_or_default().trim();
+            if field.is_empty() {
+                return Ok(None);
+            }
+            if let Ok(n) = field.parse::<i64>() {
+                return Ok(Some(Number::from(n)));
+            }
+            field
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Some)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("column {}: {:?} is not a number", &headers[i], field)
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{CsvReadOpts, CsvWriteOpts, OutputFormat};
+
+    #[test]
+    fn test_process_csv_agg() -> Result<()> {
+        let output = std::env::temp_dir().join("rcli_test_agg.ndjson");
+        let opts = CsvAggOpts {
+            input: "zcyj.csv".to_string(),
+            output: output.to_str().unwrap().to_string(),
+            group_by: vec!["Position".to_string()],
+            sum: vec!["Read Count".to_string()],
+            avg: vec!["Like Count".to_string()],
+            min: vec![],
+            max: vec!["Read Count".to_string()],
+            count: true,
+            read: CsvReadOpts::default(),
+            write: CsvWriteOpts {
+                format: OutputFormat::Ndjson,
+                ..Default::default()
+            },
+        };
+        process_csv_agg(&opts)?;
+        let rows = std::fs::read_to_string(&output)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Value>, _>>()?;
+        std::fs::remove_file(output)?;
+
+        assert_eq!(rows[0]["Position"], "1");
+        let keys: Vec<&str> = rows[0]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "Position",
+                "count",
+                "sum(Read Count)",
+                "avg(Like Count)",
+                "max(Read Count)"
+            ]
+        );
+        assert_eq!(rows[0]["max(Read Count)"], 100001);
+        let total: u64 = rows.iter().map(|r| r["count"].as_u64().unwrap()).sum();
+        assert_eq!(total, 339);
+        assert!(rows
+            .iter()
+            .all(|r| r["sum(Read Count)"].is_i64() && r["avg(Like Count)"].is_f64()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_agg_column_names() -> Result<()> {
+        let input = std::env::temp_dir().join("rcli_test_agg_names.csv");
+        let output = std::env::temp_dir().join("rcli_test_agg_names.ndjson");
+        std::fs::write(&input, "count,x\na,1\na,2\n")?;
+        let agg = |group_by: &[&str], sum: &[&str], count: bool| {
+            let opts = CsvAggOpts {
+                input: input.to_str().unwrap().to_string(),
+                output: output.to_str().unwrap().to_string(),
+                group_by: group_by.iter().map(|s| s.to_string()).collect(),
+                sum: sum.iter().map(|s| s.to_string()).collect(),
+                avg: vec![],
+                min: vec![],
+                max: vec![],
+                count,
+                read: CsvReadOpts::default(),
+                write: CsvWriteOpts {
+                    format: OutputFormat::Ndjson,
+                    ..Default::default()
+                },
+            };
+            process_csv_agg(&opts)?;
+            let row: Value = serde_json::from_str(&std::fs::read_to_string(&output)?)?;
+            Ok::<_, anyhow::Error>(row)
+        };
+
+        // 分组列名与 count 冲突
+        assert!(agg(&["count"], &[], false).is_err());
+        assert!(agg(&["count"], &["x"], true).is_err());
+        assert_eq!(
+            agg(&["count"], &["x"], false)?,
+            serde_json::json!({"count": "a", "sum(x)": 3})
+        );
+        // 重复的聚合（包括按名称和按下标指定同一列）只输出一次
+        assert_eq!(
+            agg(&[], &["x", "x", "2"], false)?,
+            serde_json::json!({"sum(x)": 3})
+        );
+
+        std::fs::remove_file(input)?;
+        std::fs::remove_file(output)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use super::{csv_convert::build_reader, csv_errors::RowErrors};
+use crate::{cli::CsvCatOpts, get_writer};
+use anyhow::Result;
+use csv::WriterBuilder;
+use std::collections::HashMap;
+
+///
+/// 按顺序拼接多个csv文件，按列名对齐表头
+///
+/// 输出的列为所有文件列的并集，按首次出现的顺序排列；文件中缺少的列输出为空。
+/// 同一文件中的同名列按出现次序分别对应
+///
+pub fn process_csv_cat(opts: &CsvCatOpts) -> Result<()> {
+    if opts.inputs.iter().filter(|input| *input == "-").count() > 1 {
+        anyhow::bail!("standard input can only be used once");
+    }
+    // 先读取所有文件的表头确定输出列，reader 保持打开，标准输入也只读取一次
+    let mut columns: Vec<String> = Vec::new();
+    let mut index: HashMap<(String, usize), usize> = HashMap::new();
+    let mut sources = Vec::with_capacity(opts.inputs.len());
+    for input in &opts.inputs {
+        let (reader, headers) = build_reader(input, &opts.read)?;
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let mapping: Vec<usize> = headers
+            .iter()
+            .map(|h| {
+                let n = seen.entry(h).or_default();
+                *n += 1;
+                *index.entry((h.to_string(), *n)).or_insert_with(|| {
+                    columns.push(h.to_string());
+                    columns.len() - 1
+                })
+            })
+            .collect();
+        sources.push((reader, mapping));
+    }
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(opts.read.delimiter)
+        .from_writer(get_writer(&opts.output)?);
+    writer.write_record(&columns)?;
+    let mut errors = RowErrors::new(&opts.read)?;
+    let mut fields = vec![String::new(); columns.len()];
+    for ((mut reader, mapping), input) in sources.into_iter().zip(&opts.inputs) {
+        for result in reader.records() {
+            let record = match result {
+                Ok(record) => record,
+                Err(e) => {
+                    errors.handle(
+                        e.position().map(|p| p.line()),
+                        anyhow::Error::from(e).context(input.clone()),
+                    )?;
+                    continue;
+                }
+            };
+            fields.iter_mut().for_each(String::clear);
+            // --flexible 时多出表头的字段被丢弃
+            for (field, &i) in record.iter().zip(&mapping) {
+                fields[i].push_str(field);
+            }
+            writer.write_record(&fields)?;
+        }
+    }
+    errors.finish()?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::CsvReadOpts;
+
+    #[test]
+    fn test_process_csv_cat() -> Result<()> {
+        let output = std::env::temp_dir().join("rcli_test_cat.csv");
+        process_csv_cat(&CsvCatOpts {
+            inputs: vec![
+                "fixtures/join_left.csv".into(),
+                "fixtures/join_right.csv".into(),
+            ],
+            output: output.to_str().unwrap().to_string(),
+            read: CsvReadOpts::default(),
+        })?;
+        let s = std::fs::read_to_string(&output)?;
+        std::fs::remove_file(output)?;
+        assert_eq!(
+            s,
+            "id,name,user_id,score\n1,alice,,\n2,bob,,\n3,carol,,\n,A,1,90\n,A2,1,95\n,D,4,70\n"
+        );
+        Ok(())
+    }
+}
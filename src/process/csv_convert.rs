@@ -1,11 +1,23 @@
-use csv::Reader;
+use csv::{Reader, ReaderBuilder, StringRecord};
 // use serde::{Deserialize, Serialize};
-use crate::cli::OutputFormat;
-use serde_json::Value;
-use std::fs;
+use super::{
+    csv_encoding::decode_reader,
+    csv_errors::RowErrors,
+    csv_filter::Filter,
+    csv_mask::Masker,
+    csv_output::row_writer,
+    csv_reverse::Unflattener,
+    csv_types::{convert_value, infer_value, DatetimeNormalizer},
+};
+use crate::{
+    cli::{ColumnType, CsvConvertOpts, CsvReadOpts, CsvWriteOpts, MaskRule, OutputFormat},
+    get_reader, get_writer,
+};
+use serde_json::{Map, Value};
+use std::io::Read;
 
-///
-/// Csv文件中的一条记录
+//
+// Csv文件中的一条记录
 // #[derive(Debug, Serialize, Deserialize)]
 // #[serde(rename_all = "PascalCase")]
 // pub struct CsvRecord {
@@ -22,12 +34,12 @@ use std::fs;
 //     link: String,
 // }
 
-///
-/// 读取输入的csv文件，并转换为json写入到给定的文件
-/// @param input 输入的csv文件路径
-/// @param output 输出的json文件路径
-/// @return Ok(()) if success, Err(anyhow::Error) if failed
-///
+//
+// 读取输入的csv文件，并转换为json写入到给定的文件
+// @param input 输入的csv文件路径
+// @param output 输出的json文件路径
+// @return Ok(()) if success, Err(anyhow::Error) if failed
+//
 // pub fn process_csv_0(input: &str, output: &str) -> anyhow::Result<()> {
 //     let mut reader = Reader::from_path(input)?;
 //     let mut ret = Vec::with_capacity(128);
@@ -41,28 +53,240 @@ use std::fs;
 //     Ok(())
 // }
 
-pub fn process_csv(input: &str, output: String, format: OutputFormat) -> anyhow::Result<()> {
-    let mut reader = Reader::from_path(input)?;
-    let mut ret = Vec::with_capacity(128);
-    let headers = reader.headers()?.clone();
+pub fn process_csv(
+    input: &str,
+    output: String,
+    opts: &CsvReadOpts,
+    convert: &CsvConvertOpts,
+    write: &CsvWriteOpts,
+) -> anyhow::Result<()> {
+    let (mut reader, headers) = build_reader(input, opts)?;
+    let builder = RowBuilder::new(headers, convert)?;
+    let filter = convert
+        .filter
+        .as_deref()
+        .map(|src| Filter::parse(src, &builder.headers()))
+        .transpose()?;
+    let unflattener = if convert.unflatten {
+        if matches!(write.format, OutputFormat::Csv | OutputFormat::Markdown) {
+            anyhow::bail!("--unflatten is not supported for {} output", write.format);
+        }
+        Some(Unflattener::new(&builder.headers())?)
+    } else {
+        None
+    };
+    // 逐行转换并写出，不在内存中保存全部记录
+    let mut writer = row_writer(get_writer(&output)?, builder.headers(), write)?;
+    let mut errors = RowErrors::new(opts)?;
     for result in reader.records() {
-        let record = result?;
-        // headers.iter() 使用 hearders 的迭代器
-        // record.iter() 使用 record 的迭代器
-        // zip() 将两个迭代器合并成一个元组的迭代器 [(header, value), ...]
-        // collect::<Value>() 将元组迭代器收集成一个 JSON Value
-        let json_value = headers.iter().zip(record.iter()).collect::<Value>();
-        ret.push(json_value);
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        // --where 在脱敏前的值上求值，脱敏列也能按原值过滤
+        let mut row = match builder.convert(&record) {
+            Ok(row) => row,
+            Err(e) => {
+                errors.handle(record.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        if !filter.as_ref().is_none_or(|f| f.matches(&row)) {
+            continue;
+        }
+        builder.mask(&record, &mut row)?;
+        match &unflattener {
+            Some(unflattener) => writer.write_row(&unflattener.unflatten(&row))?,
+            None => writer.write_row(&row)?,
+        }
     }
+    writer.finish()?;
+    errors.finish()?;
+
+    Ok(())
+}
 
-    let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
+///
+/// 按照读取选项构建csv Reader，并返回最终使用的表头
+///
+/// 无表头时优先使用 --columns 提供的列名，否则按列数生成 col1, col2, ...
+///
+pub(crate) fn build_reader(
+    input: &str,
+    opts: &CsvReadOpts,
+) -> anyhow::Result<(Reader<Box<dyn Read>>, StringRecord)> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(opts.delimiter)
+        .has_headers(opts.header)
+        .flexible(opts.flexible)
+        .from_reader(decode_reader(get_reader(input)?, &opts.encoding)?);
+    // has_headers(false) 时 headers() 返回首行数据（不会被消费），仅用于确定列数
+    let first = reader.headers()?.clone();
+    let headers = match &opts.columns {
+        Some(columns) => {
+            if columns.len() != first.len() {
+                anyhow::bail!(
+                    "--columns has {} names but the file has {} columns",
+                    columns.len(),
+                    first.len()
+                );
+            }
+            StringRecord::from(columns.clone())
+        }
+        None if opts.header => first,
+        None => (1..=first.len()).map(|i| format!("col{}", i)).collect(),
     };
+    Ok((reader, headers))
+}
+///
+/// 将csv记录按表头转换为 JSON Value，并按列应用投影、重命名和类型转换
+///
+pub(crate) struct RowBuilder {
+    columns: Vec<Column>,
+    infer: bool,
+    masker: Masker,
+}
+
+/// 一个输出列：对应的输入列序号、输出列名以及指定的类型
+struct Column {
+    index: usize,
+    name: String,
+    ty: Option<ColumnType>,
+    mask: Option<MaskRule>,
+    datetime: Option<DatetimeNormalizer>,
+}
 
-    fs::write(output, content)?;
+impl RowBuilder {
+    pub(crate) fn new(headers: StringRecord, opts: &CsvConvertOpts) -> anyhow::Result<Self> {
+        // --select 决定输出哪些列以及顺序，未指定时输出全部列
+        let mut indices = if opts.select.is_empty() {
+            (0..headers.len()).collect::<Vec<_>>()
+        } else {
+            opts.select
+                .iter()
+                .map(|spec| resolve_column(&headers, spec))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        let excluded = opts
+            .exclude
+            .iter()
+            .map(|spec| resolve_column(&headers, spec))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        indices.retain(|i| !excluded.contains(i));
 
-    Ok(())
+        let mut names: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+        for (from, to) in &opts.rename {
+            names[resolve_column(&headers, from)?] = to.clone();
+        }
+        let mut types = vec![None; headers.len()];
+        for (name, ty) in &opts.types {
+            types[resolve_column(&headers, name)?] = Some(*ty);
+        }
+        let mut masks = vec![None; headers.len()];
+        for (name, rule) in &opts.mask {
+            masks[resolve_column(&headers, name)?] = Some(*rule);
+        }
+        // datetime 类型的列与 --datetime 自动识别格式的列使用相同的输出形式
+        let mut formats: Vec<Option<Option<String>>> = types
+            .iter()
+            .map(|ty| (*ty == Some(ColumnType::Datetime)).then_some(None))
+            .collect();
+        for (name, format) in &opts.datetime {
+            formats[resolve_column(&headers, name)?] = Some(format.clone());
+        }
+        let masker = Masker::new(
+            opts.mask.iter().map(|(_, rule)| rule),
+            opts.mask_key.as_deref(),
+        )?;
+
+        let columns = indices
+            .into_iter()
+            .map(|index| Column {
+                index,
+                name: names[index].clone(),
+                ty: types[index],
+                mask: masks[index],
+                datetime: formats[index]
+                    .clone()
+                    .map(|format| DatetimeNormalizer::new(format, opts.tz, opts.datetime_output)),
+            })
+            .collect();
+        Ok(Self {
+            columns,
+            infer: opts.infer,
+            masker,
+        })
+    }
+
+    /// 输出的列名，决定 csv/markdown 等表格格式的列顺序
+    pub(crate) fn headers(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    pub(crate) fn build(&self, record: &StringRecord) -> anyhow::Result<Value> {
+        let mut row = self.convert(record)?;
+        self.mask(record, &mut row)?;
+        Ok(row)
+    }
+
+    /// 按类型转换但不脱敏；脱敏列保留原始字符串，供 --where 按原值过滤
+    pub(crate) fn convert(&self, record: &StringRecord) -> anyhow::Result<Value> {
+        // 按输出列从 record 中取出对应的字段，组装成 {列名: 值}
+        let mut row = Map::with_capacity(self.columns.len());
+        for column in &self.columns {
+            // --flexible 时字段数可能少于表头，缺少的字段输出为 null
+            let Some(field) = record.get(column.index) else {
+                row.insert(column.name.clone(), Value::Null);
+                continue;
+            };
+            // 脱敏后的值始终是字符串，不再做类型转换；空单元格保持原样
+            if column.mask.is_some() && !field.is_empty() {
+                row.insert(column.name.clone(), Value::String(field.to_string()));
+                continue;
+            }
+            let value = match (&column.datetime, column.ty) {
+                (Some(datetime), _) => datetime.normalize(field),
+                (None, Some(ty)) => convert_value(field, ty),
+                (None, None) if self.infer => Ok(infer_value(field)),
+                (None, None) => Ok(Value::String(field.to_string())),
+            }
+            .map_err(|e| {
+                let line = record.position().map(|p| p.line()).unwrap_or_default();
+                anyhow::anyhow!("line {}, column {:?}: {}", line, column.name, e)
+            })?;
+            row.insert(column.name.clone(), value);
+        }
+        Ok(Value::Object(row))
+    }
+
+    /// 将 convert 结果中非空的脱敏列替换为脱敏后的值
+    pub(crate) fn mask(&self, record: &StringRecord, row: &mut Value) -> anyhow::Result<()> {
+        for column in &self.columns {
+            let (Some(rule), Some(field)) = (column.mask, record.get(column.index)) else {
+                continue;
+            };
+            if !field.is_empty() {
+                row[&column.name] = Value::String(self.masker.mask(field, rule)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// 将列名或从 1 开始的列序号解析为列下标，列名优先
+///
+pub(crate) fn resolve_column(headers: &StringRecord, spec: &str) -> anyhow::Result<usize> {
+    if let Some(idx) = headers.iter().position(|h| h == spec) {
+        return Ok(idx);
+    }
+    match spec.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= headers.len() => Ok(n - 1),
+        _ => Err(anyhow::anyhow!("Unknown column: {}", spec)),
+    }
 }
 
 // pub fn process_csv(input: &str, output: &str) -> anyhow::Result<()> {
@@ -83,3 +307,180 @@ pub fn process_csv(input: &str, output: String, format: OutputFormat) -> anyhow:
 
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_reader_with_header() -> anyhow::Result<()> {
+        let (mut reader, headers) = build_reader("assets/juventus.csv", &CsvReadOpts::default())?;
+        assert_eq!(&headers[0], "Name");
+        assert_eq!(&reader.records().next().unwrap()?[0], "Wojciech Szczesny");
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_reader_without_header() -> anyhow::Result<()> {
+        let opts = CsvReadOpts {
+            header: false,
+            ..Default::default()
+        };
+        let (mut reader, headers) = build_reader("assets/juventus.csv", &opts)?;
+        assert_eq!(headers, vec!["col1", "col2", "col3", "col4", "col5"]);
+        // 首行作为数据返回
+        assert_eq!(&reader.records().next().unwrap()?[0], "Name");
+
+        let opts = CsvReadOpts {
+            header: false,
+            columns: Some(vec!["a".into(), "b".into()]),
+            ..Default::default()
+        };
+        assert!(build_reader("assets/juventus.csv", &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_builder_types() -> anyhow::Result<()> {
+        let (mut reader, headers) = build_reader("zcyj.csv", &CsvReadOpts::default())?;
+        let opts = CsvConvertOpts {
+            infer: true,
+            types: vec![
+                ("Publish Time".into(), ColumnType::Datetime),
+                ("View Count".into(), ColumnType::String),
+            ],
+            ..Default::default()
+        };
+        let builder = RowBuilder::new(headers.clone(), &opts)?;
+        let row = builder.build(&reader.records().next().unwrap()?)?;
+        assert_eq!(row["Read Count"], serde_json::json!(100001));
+        assert_eq!(row["Position"], serde_json::json!(1));
+        assert_eq!(row["View Count"], serde_json::json!("123"));
+        // 与 --datetime 一样按 --tz 输出 RFC 3339
+        assert_eq!(
+            row["Publish Time"],
+            serde_json::json!("2024-06-19T21:26:00Z")
+        );
+
+        let opts = CsvConvertOpts {
+            types: vec![("Publish Time".into(), ColumnType::Datetime)],
+            datetime_output: crate::cli::DatetimeOutput::Epoch,
+            ..Default::default()
+        };
+        let row = RowBuilder::new(headers, &opts)?.build(&reader.records().next().unwrap()?)?;
+        assert_eq!(row["Publish Time"], serde_json::json!(1717621500));
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_builder_projection() -> anyhow::Result<()> {
+        let (mut reader, headers) = build_reader("zcyj.csv", &CsvReadOpts::default())?;
+        let opts = CsvConvertOpts {
+            select: vec!["Read Count".into(), "2".into(), "Link".into()],
+            exclude: vec!["7".into()],
+            rename: vec![("Read Count".into(), "reads".into())],
+            types: vec![("Read Count".into(), ColumnType::Int)],
+            ..Default::default()
+        };
+        let builder = RowBuilder::new(headers.clone(), &opts)?;
+        assert_eq!(builder.headers(), vec!["reads", "Title"]);
+        let row = builder.build(&reader.records().next().unwrap()?)?;
+        assert_eq!(row["reads"], serde_json::json!(100001));
+        assert_eq!(row.as_object().unwrap().len(), 2);
+
+        let opts = CsvConvertOpts {
+            select: vec!["8".into()],
+            ..Default::default()
+        };
+        assert!(RowBuilder::new(headers, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_on_error() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli_test_on_error.ndjson");
+        let output = output.to_str().unwrap().to_string();
+        let write = CsvWriteOpts {
+            format: crate::cli::OutputFormat::Ndjson,
+            ..Default::default()
+        };
+        let convert = CsvConvertOpts::default();
+        assert!(process_csv(
+            "fixtures/ragged.csv",
+            output.clone(),
+            &CsvReadOpts::default(),
+            &convert,
+            &write
+        )
+        .is_err());
+
+        let opts = CsvReadOpts {
+            on_error: crate::cli::OnError::Skip,
+            ..Default::default()
+        };
+        process_csv(
+            "fixtures/ragged.csv",
+            output.clone(),
+            &opts,
+            &convert,
+            &write,
+        )?;
+        assert_eq!(std::fs::read_to_string(&output)?.lines().count(), 1);
+
+        let opts = CsvReadOpts {
+            flexible: true,
+            ..Default::default()
+        };
+        process_csv(
+            "fixtures/ragged.csv",
+            output.clone(),
+            &opts,
+            &convert,
+            &write,
+        )?;
+        let rows: Vec<Value> = std::fs::read_to_string(&output)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[1]["city"], Value::Null);
+        assert_eq!(rows[2]["city"], "Rome");
+        std::fs::remove_file(output)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_where_on_masked_column() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli_test_where_masked.ndjson");
+        let output = output.to_str().unwrap().to_string();
+        let convert = CsvConvertOpts {
+            filter: Some("name = 'bob'".to_string()),
+            mask: vec![("name".to_string(), MaskRule::Redact)],
+            ..Default::default()
+        };
+        let write = CsvWriteOpts {
+            format: OutputFormat::Ndjson,
+            ..Default::default()
+        };
+        process_csv(
+            "fixtures/ragged.csv",
+            output.clone(),
+            &CsvReadOpts {
+                flexible: true,
+                ..Default::default()
+            },
+            &convert,
+            &write,
+        )?;
+        // 按脱敏前的值过滤，输出的仍是脱敏后的值
+        let rows: Vec<Value> = std::fs::read_to_string(&output)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["name"], "[REDACTED]");
+        assert_eq!(rows[0]["age"], "25");
+        std::fs::remove_file(output)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,320 @@
+use super::{
+    csv_convert::{build_reader, resolve_column, RowBuilder},
+    csv_errors::RowErrors,
+    csv_output::value_to_field,
+};
+use crate::cli::{CsvConvertOpts, CsvReadOpts};
+use anyhow::Result;
+use csv::StringRecord;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+///
+/// 按主键比较两个csv文件，输出新增、删除和修改的行
+///
+/// - 修改的行只比较两个文件共有的列，并给出每列的旧值和新值
+/// - 只存在于一个文件中的列在摘要中单独列出
+/// - json 为 true 时输出 JSON patch 风格的操作列表，否则输出文本摘要
+///
+/// 旧文件按主键保存在内存中，新文件逐行比较；返回两个文件是否存在差异
+///
+pub fn process_csv_diff(
+    old: &str,
+    new: &str,
+    opts: &CsvReadOpts,
+    key: &[String],
+    json: bool,
+    writer: &mut dyn Write,
+) -> Result<bool> {
+    let (mut old_reader, old_headers) = build_reader(old, opts)?;
+    let (mut new_reader, new_headers) = build_reader(new, opts)?;
+    let old_builder = RowBuilder::new(old_headers.clone(), &CsvConvertOpts::default())?;
+    let new_builder = RowBuilder::new(new_headers.clone(), &CsvConvertOpts::default())?;
+    let old_keys = resolve_keys(&old_headers, key)?;
+    let new_keys = resolve_keys(&new_headers, key)?;
+    let common: Vec<String> = new_headers
+        .iter()
+        .filter(|h| old_headers.iter().any(|o| o == *h))
+        .map(|h| h.to_string())
+        .collect();
+
+    // 旧文件：主键 -> (读取顺序, 行)
+    let mut old_rows = HashMap::new();
+    let mut count_old = 0;
+    let mut errors = RowErrors::new(opts)?;
+    for result in old_reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line());
+                errors.handle(line, anyhow::Error::from(e).context(old.to_string()))?;
+                continue;
+            }
+        };
+        let id = key_of(&record, &old_keys);
+        let row = old_builder.build(&record)?;
+        if old_rows.insert(id.clone(), (count_old, row)).is_some() {
+            anyhow::bail!("{}: duplicate key {:?}", old, id);
+        }
+        count_old += 1;
+    }
+
+    let mut ops = Vec::new();
+    let mut seen = HashSet::new();
+    let mut count_new = 0;
+    for result in new_reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line());
+                errors.handle(line, anyhow::Error::from(e).context(new.to_string()))?;
+                continue;
+            }
+        };
+        let id = key_of(&record, &new_keys);
+        if !seen.insert(id.clone()) {
+            anyhow::bail!("{}: duplicate key {:?}", new, id);
+        }
+        count_new += 1;
+        let row = new_builder.build(&record)?;
+        let Some((_, old_row)) = old_rows.remove(&id) else {
+            ops.push(DiffOp::Add {
+                key: key_object(key, &id),
+                value: row,
+            });
+            continue;
+        };
+        let mut changes = Map::new();
+        for column in &common {
+            if old_row[column] != row[column] {
+                let change = Change {
+                    old: old_row[column].clone(),
+                    new: row[column].clone(),
+                };
+                changes.insert(column.clone(), serde_json::to_value(change)?);
+            }
+        }
+        if !changes.is_empty() {
+            ops.push(DiffOp::Replace {
+                key: key_object(key, &id),
+                changes,
+            });
+        }
+    }
+    errors.finish()?;
+    // 新文件中没有的行按旧文件中的顺序输出
+    let mut removed: Vec<_> = old_rows.into_iter().collect();
+    removed.sort_by_key(|(_, (i, _))| *i);
+    for (id, (_, row)) in removed {
+        ops.push(DiffOp::Remove {
+            key: key_object(key, &id),
+            value: row,
+        });
+    }
+
+    let columns_added: Vec<&str> = new_headers
+        .iter()
+        .filter(|h| !common.iter().any(|c| c == h))
+        .collect();
+    let columns_removed: Vec<&str> = old_headers
+        .iter()
+        .filter(|h| !common.iter().any(|c| c == h))
+        .collect();
+    let changed = !ops.is_empty() || !columns_added.is_empty() || !columns_removed.is_empty();
+
+    if json {
+        let doc = DiffDocument {
+            columns_added,
+            columns_removed,
+            ops,
+        };
+        serde_json::to_writer_pretty(&mut *writer, &doc)?;
+        writeln!(writer)?;
+    } else {
+        let count = |f: fn(&DiffOp) -> bool| ops.iter().filter(|op| f(op)).count();
+        writeln!(
+            writer,
+            "{} added, {} removed, {} changed ({} rows in old, {} rows in new)",
+            count(|op| matches!(op, DiffOp::Add { .. })),
+            count(|op| matches!(op, DiffOp::Remove { .. })),
+            count(|op| matches!(op, DiffOp::Replace { .. })),
+            count_old,
+            count_new,
+        )?;
+        if !columns_added.is_empty() {
+            writeln!(writer, "columns added: {}", columns_added.join(", "))?;
+        }
+        if !columns_removed.is_empty() {
+            writeln!(writer, "columns removed: {}", columns_removed.join(", "))?;
+        }
+        for op in &ops {
+            op.write_summary(writer)?;
+        }
+    }
+    writer.flush()?;
+    Ok(changed)
+}
+
+/// JSON 输出：列的变化以及逐行的操作
+#[derive(Debug, Serialize)]
+struct DiffDocument<'a> {
+    columns_added: Vec<&'a str>,
+    columns_removed: Vec<&'a str>,
+    ops: Vec<DiffOp>,
+}
+
+///
+/// 一行的差异，序列化为 {"op": "add" | "remove" | "replace", "key": {...}, ...}
+///
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum DiffOp {
+    Add {
+        key: Map<String, Value>,
+        value: Value,
+    },
+    Remove {
+        key: Map<String, Value>,
+        value: Value,
+    },
+    Replace {
+        key: Map<String, Value>,
+        changes: Map<String, Value>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct Change {
+    old: Value,
+    new: Value,
+}
+
+impl DiffOp {
+    fn write_summary(&self, writer: &mut dyn Write) -> std::io::Result<()> {
+        let fmt_key = |key: &Map<String, Value>| {
+            key.iter()
+                .map(|(k, v)| format!("{}={}", k, value_to_field(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            DiffOp::Add { key, .. } => writeln!(writer, "+ {}", fmt_key(key)),
+            DiffOp::Remove { key, .. } => writeln!(writer, "- {}", fmt_key(key)),
+            DiffOp::Replace { key, changes } => {
+                writeln!(writer, "~ {}", fmt_key(key))?;
+                for (column, change) in changes {
+                    writeln!(
+                        writer,
+                        "    {}: {:?} -> {:?}",
+                        column,
+                        value_to_field(&change["old"]),
+                        value_to_field(&change["new"])
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn resolve_keys(headers: &StringRecord, key: &[String]) -> Result<Vec<usize>> {
+    key.iter()
+        .map(|spec| resolve_column(headers, spec))
+        .collect()
+}
+
+fn key_of(record: &StringRecord, keys: &[usize]) -> Vec<String> {
+    keys.iter()
+        .map(|&i| record.get(i).unwrap_or_default().to_string())
+        .collect()
+}
+
+fn key_object(key: &[String], id: &[String]) -> Map<String, Value> {
+    key.iter()
+        .zip(id)
+        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_process_csv_diff() -> Result<()> {
+        let key = vec!["id".to_string()];
+        let mut buf = Vec::new();
+        let changed = process_csv_diff(
+            "fixtures/diff_old.csv",
+            "fixtures/diff_new.csv",
+            &CsvReadOpts::default(),
+            &key,
+            true,
+            &mut buf,
+        )?;
+        assert!(changed);
+        let doc: Value = serde_json::from_slice(&buf)?;
+        assert_eq!(doc["columns_added"], json!(["email"]));
+        assert_eq!(doc["columns_removed"], json!(["age"]));
+        assert_eq!(
+            doc["ops"],
+            json!([
+                {"op": "replace", "key": {"id": "2"}, "changes": {"name": {"old": "bob", "new": "bobby"}}},
+                {"op": "add", "key": {"id": "4"}, "value": {"id": "4", "name": "dave", "email": "d@x.com"}},
+                {"op": "remove", "key": {"id": "3"}, "value": {"id": "3", "name": "carol", "age": "41"}},
+            ])
+        );
+
+        let mut buf = Vec::new();
+        process_csv_diff(
+            "fixtures/diff_old.csv",
+            "fixtures/diff_new.csv",
+            &CsvReadOpts::default(),
+            &key,
+            false,
+            &mut buf,
+        )?;
+        let summary = String::from_utf8(buf)?;
+        assert!(
+            summary.starts_with("1 added, 1 removed, 1 changed (3 rows in old, 3 rows in new)\n")
+        );
+        assert!(summary.contains("~ id=2\n    name: \"bob\" -> \"bobby\"\n"));
+
+        let mut buf = Vec::new();
+        let changed = process_csv_diff(
+            "zcyj.csv",
+            "zcyj.csv",
+            &CsvReadOpts::default(),
+            &["Link".to_string()],
+            false,
+            &mut buf,
+        )?;
+        assert!(!changed);
+
+        // 无法解析的行按 --on-error 处理
+        let key = vec!["name".to_string()];
+        let diff = |opts: &CsvReadOpts| {
+            let mut buf = Vec::new();
+            process_csv_diff(
+                "fixtures/ragged.csv",
+                "fixtures/ragged.csv",
+                opts,
+                &key,
+                false,
+                &mut buf,
+            )
+        };
+        assert!(diff(&CsvReadOpts::default()).is_err());
+        let opts = CsvReadOpts {
+            on_error: crate::cli::OnError::Skip,
+            ..Default::default()
+        };
+        assert!(!diff(&opts)?);
+        Ok(())
+    }
+}
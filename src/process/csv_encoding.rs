@@ -0,0 +1,150 @@
+use anyhow::Result;
+use encoding_rs::{Encoding, GB18030, UTF_16BE, UTF_16LE, UTF_8};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::io::{Cursor, Read};
+
+/// 自动识别编码时读取的样本大小（字节）
+const SNIFF_SIZE: usize = 64 * 1024;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+///
+/// 将输入转码为 UTF-8
+///
+/// encoding 为 "auto" 时：有 BOM 按 BOM 识别；样本是合法 UTF-8 则按 UTF-8；
+/// 大量 0 字节按 UTF-16 处理；否则按 GB18030（兼容 GBK）处理，不再识别其他编码
+///
+/// 无论自动识别还是显式指定，UTF-8 都不转码，由 csv reader 严格校验
+///
+pub(crate) fn decode_reader(mut reader: Box<dyn Read>, encoding: &str) -> Result<Box<dyn Read>> {
+    let (encoding, reader): (&'static Encoding, Box<dyn Read>) = if encoding == "auto" {
+        let mut sample = Vec::with_capacity(SNIFF_SIZE);
+        (&mut reader)
+            .take(SNIFF_SIZE as u64)
+            .read_to_end(&mut sample)?;
+        let encoding = detect_encoding(&sample);
+        if encoding == UTF_8 {
+            return Ok(strict_utf8(sample, reader));
+        }
+        (encoding, Box::new(Cursor::new(sample).chain(reader)))
+    } else {
+        let encoding = Encoding::for_label(encoding.as_bytes())
+            .ok_or_else(|| anyhow::anyhow!("Unsupported encoding: {}", encoding))?;
+        if encoding == UTF_8 {
+            let mut sample = Vec::with_capacity(UTF8_BOM.len());
+            (&mut reader)
+                .take(UTF8_BOM.len() as u64)
+                .read_to_end(&mut sample)?;
+            return Ok(strict_utf8(sample, reader));
+        }
+        (encoding, reader)
+    };
+    // BOM 始终优先，并会从输出中去掉
+    let decoder = DecodeReaderBytesBuilder::new()
+        .encoding(Some(encoding))
+        .bom_override(true)
+        .build(reader);
+    Ok(Box::new(decoder))
+}
+
+/// UTF-8 不转码，只去掉 BOM：非法字节由 csv reader 严格校验并报告行号，
+/// 而不是被静默替换为 U+FFFD
+fn strict_utf8(mut sample: Vec<u8>, reader: Box<dyn Read>) -> Box<dyn Read> {
+    if sample.starts_with(UTF8_BOM) {
+        sample.drain(..UTF8_BOM.len());
+    }
+    Box::new(Cursor::new(sample).chain(reader))
+}
+
+fn detect_encoding(sample: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return encoding;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => return UTF_8,
+        // 样本末尾被截断的多字节字符不算错误
+        Err(e) if e.error_len().is_none() => return UTF_8,
+        Err(_) => {}
+    }
+    let zeros = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let half = sample.len() / 2;
+    if half > 0 && zeros(1) * 3 > half {
+        UTF_16LE
+    } else if half > 0 && zeros(0) * 3 > half {
+        UTF_16BE
+    } else {
+        GB18030
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::CsvReadOpts, process::csv_convert::build_reader};
+
+    fn decode(bytes: Vec<u8>, encoding: &str) -> Result<String> {
+        let mut s = String::new();
+        decode_reader(Box::new(Cursor::new(bytes)), encoding)?.read_to_string(&mut s)?;
+        Ok(s)
+    }
+
+    #[test]
+    fn test_decode_reader_auto() -> Result<()> {
+        let text = "标题,阅读数\n领导,100\n";
+        let (gbk, _, _) = encoding_rs::GBK.encode(text);
+        assert_eq!(decode(gbk.to_vec(), "auto")?, text);
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(text.encode_utf16().flat_map(|u| u.to_le_bytes()));
+        assert_eq!(decode(utf16, "auto")?, text);
+
+        let mut utf8_bom = vec![0xEF, 0xBB, 0xBF];
+        utf8_bom.extend(text.as_bytes());
+        assert_eq!(decode(utf8_bom, "auto")?, text);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_reader_auto_utf8_is_strict() -> Result<()> {
+        // 样本之后出现的非法字节不能被替换为 U+FFFD
+        let mut bytes = "a,b\n".repeat(SNIFF_SIZE / 4 + 1).into_bytes();
+        bytes.extend([0xFF, b'\n']);
+        assert!(decode(bytes.clone(), "auto").is_err());
+
+        let input = std::env::temp_dir().join("rcli_test_invalid_utf8.csv");
+        std::fs::write(&input, &bytes)?;
+        let (mut reader, _) = build_reader(input.to_str().unwrap(), &CsvReadOpts::default())?;
+        let err = reader.records().find_map(|r| r.err());
+        std::fs::remove_file(input)?;
+        let err = err.expect("invalid UTF-8 is reported");
+        assert_eq!(
+            err.position().map(|p| p.line()),
+            Some(SNIFF_SIZE as u64 / 4 + 2)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_reader_explicit() -> Result<()> {
+        let text = "a,b\n中,文\n";
+        let (gbk, _, _) = encoding_rs::GBK.encode(text);
+        assert_eq!(decode(gbk.to_vec(), "gbk")?, text);
+        let utf16: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect();
+        assert_eq!(decode(utf16, "utf-16be")?, text);
+
+        // 显式指定 UTF-8 与自动识别一样严格
+        let mut utf8_bom = UTF8_BOM.to_vec();
+        utf8_bom.extend(text.as_bytes());
+        assert_eq!(decode(utf8_bom, "utf-8")?, text);
+        assert_eq!(decode(b"ab".to_vec(), "utf8")?, "ab");
+        assert!(decode(b"a,\xFF\n".to_vec(), "utf-8").is_err());
+        Ok(())
+    }
+}
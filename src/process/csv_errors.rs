@@ -0,0 +1,128 @@
+use crate::{
+    cli::{CsvReadOpts, OnError},
+    get_writer,
+};
+use anyhow::Result;
+use std::io::Write;
+
+///
+/// 按照 --on-error 处理行级错误
+///
+/// - fail：直接返回错误
+/// - skip：跳过该行，结束时在标准错误输出跳过的行数
+/// - report：跳过该行，并将行号和错误写入 --error-report 文件或标准错误
+///
+pub(crate) struct RowErrors {
+    mode: OnError,
+    report: Option<Box<dyn Write>>,
+    count: usize,
+}
+
+impl RowErrors {
+    pub(crate) fn new(opts: &CsvReadOpts) -> Result<Self> {
+        let report = match (opts.on_error, &opts.error_report) {
+            (OnError::Report, Some(path)) => Some(get_writer(path)?),
+            (OnError::Report, None) => Some(Box::new(std::io::stderr()) as Box<dyn Write>),
+            (on_error, Some(_)) => {
+                anyhow::bail!(
+                    "--error-report requires --on-error report, got {}",
+                    on_error
+                )
+            }
+            _ => None,
+        };
+        Ok(Self {
+            mode: opts.on_error,
+            report,
+            count: 0,
+        })
+    }
+
+    ///
+    /// 处理一行的错误，fail 模式下返回带行号的错误，其余模式记录后返回 Ok 以便跳过该行
+    ///
+    pub(crate) fn handle(
+        &mut self,
+        line: Option<u64>,
+        err: impl Into<anyhow::Error>,
+    ) -> Result<()> {
+        let err = err.into();
+        let line = line
+            .map(|l| l.to_string())
+            .unwrap_or_else(|| "?".to_string());
+        if self.mode == OnError::Fail {
+            return Err(err.context(format!("failed to process line {}", line)));
+        }
+        self.count += 1;
+        if let Some(report) = &mut self.report {
+            writeln!(report, "line {}: {:#}", line, err)?;
+        }
+        Ok(())
+    }
+
+    /// 输出跳过的行数汇总
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if let Some(report) = &mut self.report {
+            report.flush()?;
+        }
+        if self.count > 0 {
+            eprintln!("skipped {} invalid rows", self.count);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_errors() -> Result<()> {
+        let mut errors = RowErrors::new(&CsvReadOpts::default())?;
+        let err = errors
+            .handle(Some(3), anyhow::anyhow!("bad row"))
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("line 3: bad row"));
+
+        let report = std::env::temp_dir().join("rcli_test_row_errors.txt");
+        let opts = CsvReadOpts {
+            on_error: OnError::Report,
+            error_report: Some(report.to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+        let mut errors = RowErrors::new(&opts)?;
+        errors.handle(Some(3), anyhow::anyhow!("bad row"))?;
+        errors.handle(None, anyhow::anyhow!("worse row"))?;
+        errors.finish()?;
+        assert_eq!(
+            std::fs::read_to_string(&report)?,
+            "line 3: bad row\nline ?: worse row\n"
+        );
+        std::fs::remove_file(report)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_report_implies_report() -> Result<()> {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            read: CsvReadOpts,
+        }
+        let cli = <Cli as clap::Parser>::try_parse_from(["rcli", "--error-report", "errors.txt"])?;
+        assert_eq!(cli.read.on_error, OnError::Report);
+        let cli = <Cli as clap::Parser>::try_parse_from(["rcli"])?;
+        assert_eq!(cli.read.on_error, OnError::Fail);
+
+        // 显式指定其他模式时 --error-report 不会被静默忽略
+        let cli = <Cli as clap::Parser>::try_parse_from([
+            "rcli",
+            "--on-error",
+            "skip",
+            "--error-report",
+            "errors.txt",
+        ])?;
+        assert!(RowErrors::new(&cli.read).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,425 @@
+use super::csv_output::value_to_field;
+use anyhow::Result;
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// 出错时提示的表达式来源
+const WHAT: &str = "--where expression";
+
+///
+/// 行过滤表达式，如 `"Read Count" > 50000 && Position == 1`
+///
+/// - 列名：裸标识符 `Position` 或双引号 `"Read Count"`
+/// - 字面量：数字、单引号字符串 `'abc'`、true / false / null
+/// - 比较：== != > >= < <=，两侧都能转为数字时按数字比较，否则按字符串比较
+/// - 字符串：`Title contains '领导'`，正则 `Link =~ 'idx=1'` / `!~`
+/// - 逻辑：&& || ! 以及括号
+///
+#[derive(Debug)]
+pub(crate) struct Filter {
+    expr: Expr,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Literal(Value),
+    Column(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cmp(CmpOp, Box<Expr>, Box<Expr>),
+    Contains(Box<Expr>, Box<Expr>),
+    Regex(Box<Expr>, Regex, bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CmpOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Cmp(CmpOp),
+    Match(bool),
+    Contains,
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+    Eof,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    headers: &'a [String],
+}
+
+impl Filter {
+    ///
+    /// 解析表达式，列名必须出现在 headers 中；出错时指出出错的位置
+    ///
+    pub(crate) fn parse(src: &str, headers: &[String]) -> Result<Self> {
+        let tokens = tokenize(src)?;
+        let mut parser = Parser {
+            src,
+            tokens,
+            pos: 0,
+            headers,
+        };
+        let expr = parser.parse_or()?;
+        if parser.peek() != &Token::Eof {
+            return Err(parser.error("unexpected token"));
+        }
+        Ok(Self { expr })
+    }
+
+    /// 判断一行是否满足过滤条件
+    pub(crate) fn matches(&self, row: &Value) -> bool {
+        truthy(&self.expr.eval(row))
+    }
+}
+
+impl Expr {
+    fn eval(&self, row: &Value) -> Value {
+        match self {
+            Expr::Literal(v) => v.clone(),
+            Expr::Column(name) => row.get(name).cloned().unwrap_or(Value::Null),
+            Expr::Not(e) => Value::Bool(!truthy(&e.eval(row))),
+            Expr::And(l, r) => Value::Bool(truthy(&l.eval(row)) && truthy(&r.eval(row))),
+            Expr::Or(l, r) => Value::Bool(truthy(&l.eval(row)) || truthy(&r.eval(row))),
+            Expr::Cmp(op, l, r) => Value::Bool(compare(*op, &l.eval(row), &r.eval(row))),
+            Expr::Contains(l, r) => {
+                let (l, r) = (l.eval(row), r.eval(row));
+                Value::Bool(value_to_field(&l).contains(&value_to_field(&r)))
+            }
+            Expr::Regex(e, re, negate) => {
+                let v = e.eval(row);
+                Value::Bool(re.is_match(&value_to_field(&v)) != *negate)
+            }
+        }
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    /// 生成带有出错位置标记的错误信息
+    fn error(&self, msg: &str) -> anyhow::Error {
+        let offset = self.tokens[self.pos].1;
+        syntax_error(WHAT, self.src, offset, msg)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while self.peek() == &Token::Or {
+            self.next();
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == &Token::And {
+            self.next();
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.peek() == &Token::Not {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_cmp()
+    }
+
+    fn parse_cmp(&mut self) -> Result<Expr> {
+        let left = self.parse_operand()?;
+        let expr = match self.peek().clone() {
+            Token::Cmp(op) => {
+                self.next();
+                Expr::Cmp(op, Box::new(left), Box::new(self.parse_operand()?))
+            }
+            Token::Contains => {
+                self.next();
+                Expr::Contains(Box::new(left), Box::new(self.parse_operand()?))
+            }
+            Token::Match(negate) => {
+                self.next();
+                let Token::Str(pattern) = self.peek().clone() else {
+                    return Err(self.error("expected a quoted regex pattern"));
+                };
+                let re = Regex::new(&pattern).map_err(|e| self.error(&e.to_string()))?;
+                self.next();
+                Expr::Regex(Box::new(left), re, negate)
+            }
+            _ => left,
+        };
+        Ok(expr)
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr> {
+        let expr = match self.peek().clone() {
+            Token::LParen => {
+                self.next();
+                let expr = self.parse_or()?;
+                if self.peek() != &Token::RParen {
+                    return Err(self.error("expected `)`"));
+                }
+                expr
+            }
+            Token::Num(n) => {
+                Expr::Literal(serde_json::Number::from_f64(n).map_or(Value::Null, Value::Number))
+            }
+            Token::Str(s) => Expr::Literal(Value::String(s)),
+            Token::Ident(name) => match name.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                _ if self.headers.contains(&name) => Expr::Column(name),
+                _ => return Err(self.error(&format!("unknown column {:?}", name))),
+            },
+            Token::Eof => return Err(self.error("unexpected end of expression")),
+            _ => return Err(self.error("expected a column, literal or `(`")),
+        };
+        self.next();
+        Ok(expr)
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' | ')' => {
+                chars.next();
+                if c == '(' {
+                    Token::LParen
+                } else {
+                    Token::RParen
+                }
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        // 连续两个引号表示引号本身
+                        Some((_, q)) if q == c => {
+                            if chars.peek().map(|&(_, n)| n) == Some(c) {
+                                chars.next();
+                                s.push(c);
+                            } else {
+                                break;
+                            }
+                        }
+                        Some((_, ch)) => s.push(ch),
+                        None => return Err(syntax_error(WHAT, src, start, "unterminated quote")),
+                    }
+                }
+                if c == '"' {
+                    Token::Ident(s)
+                } else {
+                    Token::Str(s)
+                }
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut end = start;
+                while let Some(&(i, ch)) = chars.peek() {
+                    if ch.is_ascii_digit() || ch == '.' || (i == start && ch == '-') {
+                        end = i + ch.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let n = src[start..end]
+                    .parse::<f64>()
+                    .map_err(|_| syntax_error(WHAT, src, start, "invalid number"))?;
+                Token::Num(n)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, ch)) = chars.peek() {
+                    if ch.is_alphanumeric() || ch == '_' {
+                        end = i + ch.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                match &src[start..end] {
+                    "contains" => Token::Contains,
+                    word => Token::Ident(word.to_string()),
+                }
+            }
+            _ => {
+                let rest = &src[start..];
+                let (token, len) = [
+                    ("&&", Token::And),
+                    ("||", Token::Or),
+                    ("==", Token::Cmp(CmpOp::Eq)),
+                    ("!=", Token::Cmp(CmpOp::Ne)),
+                    (">=", Token::Cmp(CmpOp::Ge)),
+                    ("<=", Token::Cmp(CmpOp::Le)),
+                    ("=~", Token::Match(false)),
+                    ("!~", Token::Match(true)),
+                    (">", Token::Cmp(CmpOp::Gt)),
+                    ("<", Token::Cmp(CmpOp::Lt)),
+                    ("=", Token::Cmp(CmpOp::Eq)),
+                    ("!", Token::Not),
+                ]
+                .into_iter()
+                .find(|(op, _)| rest.starts_with(op))
+                .map(|(op, token)| (token, op.len()))
+                .ok_or_else(|| {
+                    syntax_error(WHAT, src, start, &format!("unexpected character {:?}", c))
+                })?;
+                for _ in 0..len {
+                    chars.next();
+                }
+                token
+            }
+        };
+        tokens.push((token, start));
+    }
+    tokens.push((Token::Eof, src.len()));
+    Ok(tokens)
+}
+
+/// 生成类似编译器的错误提示，使用 ^ 标出出错位置
+pub(crate) fn syntax_error(what: &str, src: &str, offset: usize, msg: &str) -> anyhow::Error {
+    let column = src[..offset].chars().count();
+    anyhow::anyhow!(
+        "invalid {}: {} at column {}\n  {}\n  {}^",
+        what,
+        msg,
+        column + 1,
+        src,
+        " ".repeat(column)
+    )
+}
+
+pub(crate) fn truthy(v: &Value) -> bool {
+    match v {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        _ => true,
+    }
+}
+
+/// 尝试将值转换为数字，字符串会先去掉首尾空白
+pub(crate) fn as_number(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn compare(op: CmpOp, l: &Value, r: &Value) -> bool {
+    // null 与空字符串视为相等，其余与 null 的比较只有 != 成立
+    let is_null = |v: &Value| v.is_null() || v.as_str() == Some("");
+    if is_null(l) || is_null(r) {
+        let eq = is_null(l) && is_null(r);
+        return match op {
+            CmpOp::Eq => eq,
+            CmpOp::Ne => !eq,
+            _ => false,
+        };
+    }
+    let ord = match (as_number(l), as_number(r)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b),
+        _ => match (l, r) {
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            _ => Some(value_to_field(l).cmp(&value_to_field(r))),
+        },
+    };
+    let Some(ord) = ord else {
+        return op == CmpOp::Ne;
+    };
+    match op {
+        CmpOp::Eq => ord == Ordering::Equal,
+        CmpOp::Ne => ord != Ordering::Equal,
+        CmpOp::Gt => ord == Ordering::Greater,
+        CmpOp::Ge => ord != Ordering::Less,
+        CmpOp::Lt => ord == Ordering::Less,
+        CmpOp::Le => ord != Ordering::Greater,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn headers() -> Vec<String> {
+        ["Read Count", "Position", "Title", "Link"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_matches() -> Result<()> {
+        let row = json!({"Read Count": "100001", "Position": 1, "Title": "abc 领导", "Link": ""});
+        let cases = [
+            (r#""Read Count" > 50000 && Position == 1"#, true),
+            (r#""Read Count" > 50000 && !(Position == 1)"#, false),
+            ("Position != 1 || Title contains '领导'", true),
+            ("Title =~ '^abc' && Title !~ 'xyz'", true),
+            ("Link == null", true),
+            ("Position >= 2", false),
+            ("Title == 'abc 领导'", true),
+        ];
+        for (src, expected) in cases {
+            let filter = Filter::parse(src, &headers())?;
+            assert_eq!(filter.matches(&row), expected, "{}", src);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        let err = Filter::parse(r#""Read Count" > && Position"#, &headers()).unwrap_err();
+        assert!(err.to_string().contains("at column 16"), "{}", err);
+        let err = Filter::parse("Unknown == 1", &headers()).unwrap_err();
+        assert!(err.to_string().contains("unknown column"), "{}", err);
+        assert!(Filter::parse("Title =~ '('", &headers()).is_err());
+        assert!(Filter::parse("(Position == 1", &headers()).is_err());
+        assert!(Filter::parse("'abc", &headers()).is_err());
+    }
+}
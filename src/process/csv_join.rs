@@ -0,0 +1,542 @@
+use super::{
+    csv_convert::{build_reader, resolve_column},
+    csv_errors::RowErrors,
+    csv_output::{row_writer, RowWriter},
+    csv_sort::TempDir,
+};
+use crate::{
+    cli::{CsvJoinOpts, JoinType},
+    get_writer,
+};
+use anyhow::Result;
+use csv::{ReaderBuilder, StringRecord, Writer, WriterBuilder};
+use serde_json::{Map, Value};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+/// 溢出到磁盘时分区数的上限
+const MAX_PARTITIONS: usize = 128;
+
+/// 分区仍超出内存上限时重新分区的最大层数
+const MAX_PARTITION_DEPTH: u64 = 4;
+
+///
+/// 按连接列连接两个csv文件，支持 inner/left/right/full 连接，输出为任意 OutputFormat
+///
+/// 使用哈希连接：右表在内存中建立哈希表，逐行读取左表进行匹配，输出顺序与左表一致。
+/// 右表超出 --memory-limit 时，两表按连接键的哈希分区写入临时文件，再逐个分区连接，此时输出按分区顺序；
+/// 分区仍然超出上限时换一个哈希种子继续细分。同一个键的行无法拆分，
+/// 单个键的右表行超出上限时仍会整体载入内存，因此内存上限是尽力而为的。
+///
+/// 与 SQL 中的 NULL 一样，连接键中有空值的行不与任何行匹配
+///
+/// 输出列为左表的全部列加上右表除连接列以外的列，连接列取左表的值（左表无匹配行时取右表的值），
+/// 右表中与左表重名的列加上 _right 后缀
+///
+pub fn process_csv_join(opts: &CsvJoinOpts) -> Result<()> {
+    let (left_on, right_on) = if opts.on.is_empty() {
+        (&opts.left_on, &opts.right_on)
+    } else {
+        (&opts.on, &opts.on)
+    };
+    if left_on.len() != right_on.len() {
+        anyhow::bail!(
+            "--left-on has {} columns but --right-on has {}",
+            left_on.len(),
+            right_on.len()
+        );
+    }
+
+    if opts.left == "-" && opts.right == "-" {
+        anyhow::bail!("standard input can only be used once");
+    }
+
+    let (mut left_reader, left_headers) = build_reader(&opts.left, &opts.read)?;
+    let (mut right_reader, right_headers) = build_reader(&opts.right, &opts.read)?;
+    let joiner = Joiner::new(&left_headers, &right_headers, left_on, right_on, opts.how)?;
+    let mut writer = row_writer(get_writer(&opts.output)?, joiner.headers(), &opts.write)?;
+    let mut errors = RowErrors::new(&opts.read)?;
+
+    // 先读取右表建立哈希表，超出内存上限时改为分区连接
+    let mut table = HashTable::default();
+    let mut right_records = right_reader.records();
+    let mut spilled = false;
+    for result in right_records.by_ref() {
+        let Some(record) = check_record(result, &opts.right, &mut errors)? else {
+            continue;
+        };
+        table.insert(joiner.right_key(&record), record);
+        if table.size >= opts.memory_limit {
+            spilled = true;
+            break;
+        }
+    }
+
+    if !spilled {
+        for result in left_reader.records() {
+            let Some(record) = check_record(result, &opts.left, &mut errors)? else {
+                continue;
+            };
+            joiner.probe(&mut table, &record, &mut *writer)?;
+        }
+        joiner.finish(table, &mut *writer)?;
+    } else {
+        let dir = TempDir::new(opts.temp_dir.as_deref(), "join")?;
+        // 标准输入无法预知大小，按内存上限的 8 倍估计
+        let size = if opts.right == "-" {
+            opts.memory_limit.saturating_mul(8)
+        } else {
+            fs::metadata(&opts.right)?.len() as usize
+        };
+        let count = (size / opts.memory_limit.max(1) * 2).clamp(2, MAX_PARTITIONS);
+
+        let mut right_parts = Partitions::create(&dir, "right", count, 0)?;
+        for (record, _) in table.rows {
+            right_parts.write(&joiner.right_key(&record), &record)?;
+        }
+        for result in right_records {
+            let Some(record) = check_record(result, &opts.right, &mut errors)? else {
+                continue;
+            };
+            right_parts.write(&joiner.right_key(&record), &record)?;
+        }
+        let mut left_parts = Partitions::create(&dir, "left", count, 0)?;
+        for result in left_reader.records() {
+            let Some(record) = check_record(result, &opts.left, &mut errors)? else {
+                continue;
+            };
+            left_parts.write(&joiner.left_key(&record), &record)?;
+        }
+
+        // 相同的键一定落在同一个分区中，逐个分区在内存中连接
+        let pairs = left_parts.finish()?.into_iter().zip(right_parts.finish()?);
+        for (i, (left, right)) in pairs.enumerate() {
+            let name = i.to_string();
+            joiner.join_partition(
+                &dir,
+                &name,
+                &left,
+                &right,
+                1,
+                opts.memory_limit,
+                &mut *writer,
+            )?;
+        }
+    }
+    writer.finish()?;
+    errors.finish()?;
+    Ok(())
+}
+
+/// 按 --on-error 处理读取错误，返回 None 表示跳过该行
+fn check_record(
+    result: csv::Result<StringRecord>,
+    path: &str,
+    errors: &mut RowErrors,
+) -> Result<Option<StringRecord>> {
+    match result {
+        Ok(record) => Ok(Some(record)),
+        Err(e) => {
+            let line = e.position().map(|p| p.line());
+            errors.handle(line, anyhow::Error::from(e).context(path.to_string()))?;
+            Ok(None)
+        }
+    }
+}
+
+/// 输出列的取值来源
+enum Source {
+    Left(usize),
+    Right(usize),
+    /// 第 k 个连接列，优先取左表的值
+    Key(usize),
+}
+
+struct Joiner {
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    columns: Vec<(String, Source)>,
+    how: JoinType,
+}
+
+impl Joiner {
+    fn new(
+        left_headers: &StringRecord,
+        right_headers: &StringRecord,
+        left_on: &[String],
+        right_on: &[String],
+        how: JoinType,
+    ) -> Result<Self> {
+        let left_keys = left_on
+            .iter()
+            .map(|spec| resolve_column(left_headers, spec))
+            .collect::<Result<Vec<_>>>()?;
+        let right_keys = right_on
+            .iter()
+            .map(|spec| resolve_column(right_headers, spec))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut columns: Vec<(String, Source)> = left_headers
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let source = match left_keys.iter().position(|k| *k == i) {
+                    Some(k) => Source::Key(k),
+                    None => Source::Left(i),
+                };
+                (name.to_string(), source)
+            })
+            .collect();
+        for (j, name) in right_headers.iter().enumerate() {
+            if right_keys.contains(&j) {
+                continue;
+            }
+            let mut name = name.to_string();
+            while columns.iter().any(|(n, _)| *n == name) {
+                name.push_str("_right");
+            }
+            columns.push((name, Source::Right(j)));
+        }
+        Ok(Self {
+            left_keys,
+            right_keys,
+            columns,
+            how,
+        })
+    }
+
+    fn headers(&self) -> Vec<String> {
+        self.columns.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn left_key(&self, record: &StringRecord) -> Vec<String> {
+        key_of(record, &self.left_keys)
+    }
+
+    fn right_key(&self, record: &StringRecord) -> Vec<String> {
+        key_of(record, &self.right_keys)
+    }
+
+    fn row(&self, left: Option<&StringRecord>, right: Option<&StringRecord>) -> Value {
+        let mut row = Map::with_capacity(self.columns.len());
+        for (name, source) in &self.columns {
+            let field = match source {
+                Source::Left(i) => left.and_then(|r| r.get(*i)),
+                Source::Right(j) => right.and_then(|r| r.get(*j)),
+                Source::Key(k) => left
+                    .and_then(|r| r.get(self.left_keys[*k]))
+                    .or_else(|| right.and_then(|r| r.get(self.right_keys[*k]))),
+            };
+            let value = field.map_or(Value::Null, |f| Value::String(f.to_string()));
+            row.insert(name.clone(), value);
+        }
+        Value::Object(row)
+    }
+
+    /// 连接一对分区；右表分区超出内存上限时用新的哈希种子将两个分区继续细分
+    #[allow(clippy::too_many_arguments)]
+    fn join_partition(
+        &self,
+        dir: &TempDir,
+        name: &str,
+        left: &Path,
+        right: &Path,
+        depth: u64,
+        memory_limit: usize,
+        writer: &mut (dyn RowWriter + '_),
+    ) -> Result<()> {
+        let mut table = HashTable::default();
+        let mut right_records = open_partition(right)?.into_records();
+        let mut oversized = false;
+        for record in right_records.by_ref() {
+            let record = record?;
+            table.insert(self.right_key(&record), record);
+            if table.size >= memory_limit && depth < MAX_PARTITION_DEPTH {
+                oversized = true;
+                break;
+            }
+        }
+        if !oversized {
+            for record in open_partition(left)?.into_records() {
+                self.probe(&mut table, &record?, writer)?;
+            }
+            return self.finish(table, writer);
+        }
+
+        let size = fs::metadata(right).map(|m| m.len() as usize)?;
+        let count = (size / memory_limit.max(1) * 2).clamp(2, MAX_PARTITIONS);
+        let mut right_parts = Partitions::create(dir, &format!("right-{}", name), count, depth)?;
+        for (record, _) in std::mem::take(&mut table.rows) {
+            right_parts.write(&self.right_key(&record), &record)?;
+        }
+        for record in right_records {
+            let record = record?;
+            right_parts.write(&self.right_key(&record), &record)?;
+        }
+        let mut left_parts = Partitions::create(dir, &format!("left-{}", name), count, depth)?;
+        for record in open_partition(left)?.into_records() {
+            let record = record?;
+            left_parts.write(&self.left_key(&record), &record)?;
+        }
+        // 已经细分的分区不再需要，尽早释放磁盘空间
+        fs::remove_file(left)?;
+        fs::remove_file(right)?;
+
+        let pairs = left_parts.finish()?.into_iter().zip(right_parts.finish()?);
+        for (i, (left, right)) in pairs.enumerate() {
+            let name = format!("{}.{}", name, i);
+            self.join_partition(dir, &name, &left, &right, depth + 1, memory_limit, writer)?;
+        }
+        Ok(())
+    }
+
+    /// 用左表的一行匹配哈希表，输出所有匹配的组合；left/full 连接时无匹配也输出
+    fn probe(
+        &self,
+        table: &mut HashTable,
+        left: &StringRecord,
+        writer: &mut (dyn RowWriter + '_),
+    ) -> Result<()> {
+        let key = self.left_key(left);
+        let matches = if has_empty(&key) {
+            None
+        } else {
+            table.index.get(&key)
+        };
+        match matches {
+            Some(matches) => {
+                for &i in matches {
+                    table.rows[i].1 = true;
+                    writer.write_row(&self.row(Some(left), Some(&table.rows[i].0)))?;
+                }
+            }
+            None if matches!(self.how, JoinType::Left | JoinType::Full) => {
+                writer.write_row(&self.row(Some(left), None))?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// right/full 连接时输出右表中未匹配的行
+    fn finish(&self, table: HashTable, writer: &mut (dyn RowWriter + '_)) -> Result<()> {
+        if matches!(self.how, JoinType::Right | JoinType::Full) {
+            for (record, matched) in &table.rows {
+                if !matched {
+                    writer.write_row(&self.row(None, Some(record)))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 连接键中有空值时不参与匹配
+fn has_empty(key: &[String]) -> bool {
+    key.iter().any(|k| k.is_empty())
+}
+
+fn key_of(record: &StringRecord, keys: &[usize]) -> Vec<String> {
+    keys.iter()
+        .map(|&i| record.get(i).unwrap_or_default().to_string())
+        .collect()
+}
+
+///
+/// 右表的哈希表：行按读取顺序保存，并记录是否被匹配过
+///
+#[derive(Default)]
+struct HashTable {
+    rows: Vec<(StringRecord, bool)>,
+    index: HashMap<Vec<String>, Vec<usize>>,
+    /// 粗略估计的内存占用
+    size: usize,
+}
+
+impl HashTable {
+    fn insert(&mut self, key: Vec<String>, record: StringRecord) {
+        self.size += record.as_slice().len()
+            + record.len() * size_of::<usize>()
+            + key
+                .iter()
+                .map(|k| k.len() + size_of::<String>())
+                .sum::<usize>()
+            + size_of::<(StringRecord, bool)>();
+        if !has_empty(&key) {
+            self.index.entry(key).or_default().push(self.rows.len());
+        }
+        self.rows.push((record, false));
+    }
+}
+
+/// 按连接键的哈希将行写入若干个临时文件，每一层分区使用不同的哈希种子
+struct Partitions {
+    writers: Vec<Writer<File>>,
+    paths: Vec<PathBuf>,
+    seed: u64,
+}
+
+impl Partitions {
+    fn create(dir: &TempDir, name: &str, count: usize, seed: u64) -> Result<Self> {
+        let paths: Vec<PathBuf> = (0..count)
+            .map(|i| dir.file(&format!("{}-{}.csv", name, i)))
+            .collect();
+        let writers = paths
+            .iter()
+            .map(|path| WriterBuilder::new().flexible(true).from_path(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            writers,
+            paths,
+            seed,
+        })
+    }
+
+    fn write(&mut self, key: &[String], record: &StringRecord) -> Result<()> {
+        let mut hasher = DefaultHasher::new();
+        self.seed.hash(&mut hasher);
+        key.hash(&mut hasher);
+        let i = (hasher.finish() % self.writers.len() as u64) as usize;
+        self.writers[i].write_record(record)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<PathBuf>> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+        Ok(self.paths)
+    }
+}
+
+fn open_partition(path: &Path) -> Result<csv::Reader<File>> {
+    Ok(ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{CsvReadOpts, CsvWriteOpts, OutputFormat};
+
+    fn join(how: JoinType, memory_limit: usize) -> Result<Vec<Value>> {
+        join_files(
+            "fixtures/join_left.csv",
+            "fixtures/join_right.csv",
+            how,
+            memory_limit,
+        )
+    }
+
+    fn join_files(
+        left: &str,
+        right: &str,
+        how: JoinType,
+        memory_limit: usize,
+    ) -> Result<Vec<Value>> {
+        let name = Path::new(left)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let output = std::env::temp_dir().join(format!(
+            "rcli_test_join_{}_{}_{}.ndjson",
+            name, how, memory_limit
+        ));
+        let opts = CsvJoinOpts {
+            left: left.to_string(),
+            right: right.to_string(),
+            on: vec![],
+            left_on: vec!["id".to_string()],
+            right_on: vec!["user_id".to_string()],
+            how,
+            output: output.to_str().unwrap().to_string(),
+            memory_limit,
+            temp_dir: None,
+            read: CsvReadOpts::default(),
+            write: CsvWriteOpts {
+                format: OutputFormat::Ndjson,
+                ..Default::default()
+            },
+        };
+        process_csv_join(&opts)?;
+        let rows = fs::read_to_string(&output)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Value>, _>>()?;
+        fs::remove_file(output)?;
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_process_csv_join() -> Result<()> {
+        let rows = join(JoinType::Inner, 1 << 20)?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0],
+            serde_json::json!({"id": "1", "name": "alice", "score": "90", "name_right": "A"})
+        );
+        assert_eq!(join(JoinType::Left, 1 << 20)?.len(), 4);
+
+        let rows = join(JoinType::Right, 1 << 20)?;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2]["id"], "4");
+        assert_eq!(rows[2]["name"], Value::Null);
+
+        // 分区连接与内存中连接的结果相同（顺序可能不同）
+        for how in [
+            JoinType::Inner,
+            JoinType::Left,
+            JoinType::Right,
+            JoinType::Full,
+        ] {
+            let mut in_memory: Vec<String> =
+                join(how, 1 << 20)?.iter().map(|v| v.to_string()).collect();
+            let mut spilled: Vec<String> = join(how, 1)?.iter().map(|v| v.to_string()).collect();
+            in_memory.sort();
+            spilled.sort();
+            assert_eq!(in_memory, spilled, "{}", how);
+        }
+        assert_eq!(join(JoinType::Full, 1)?.len(), 5);
+
+        // 标准输入只能读取一次
+        let err = join_files("-", "-", JoinType::Inner, 1 << 20).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("standard input can only be used once"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_skips_empty_keys() -> Result<()> {
+        let dir = TempDir::new(None, "join-test")?;
+        let left = dir.file("empty_left.csv");
+        let right = dir.file("empty_right.csv");
+        fs::write(&left, "id,name\n,x\n1,a\n")?;
+        fs::write(&right, "user_id,score\n,9\n1,5\n")?;
+        let (left, right) = (left.to_str().unwrap(), right.to_str().unwrap());
+        for memory_limit in [1 << 20, 1] {
+            let rows = join_files(left, right, JoinType::Inner, memory_limit)?;
+            assert_eq!(
+                rows,
+                vec![serde_json::json!({"id": "1", "name": "a", "score": "5"})]
+            );
+            // 空键的行只作为未匹配的行输出
+            let rows = join_files(left, right, JoinType::Full, memory_limit)?;
+            assert_eq!(rows.len(), 3);
+            assert_eq!(rows.iter().filter(|r| r["id"] == "").count(), 2);
+        }
+
+        // 标准输入只能读取一次
+        let err = join_files("-", "-", JoinType::Inner, 1 << 20).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("standard input can only be used once"));
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use super::text::KeyedHasher;
+use crate::cli::MaskRule;
+use anyhow::Result;
+
+/// redact 脱敏后的值
+const REDACTED: &str = "[REDACTED]";
+
+///
+/// 按列的脱敏规则处理单元格
+///
+/// hash 使用带密钥的 BLAKE3，输出十六进制摘要：同一密钥下相同的值得到相同的结果，
+/// 脱敏后的文件之间仍然可以按该列关联，而没有密钥无法通过枚举还原原值
+///
+pub(crate) struct Masker {
+    key: Option<KeyedHasher>,
+}
+
+impl Masker {
+    /// 只有存在 hash 规则时才需要并加载密钥文件
+    pub(crate) fn new<'a>(
+        rules: impl IntoIterator<Item = &'a MaskRule>,
+        key: Option<&str>,
+    ) -> Result<Self> {
+        if !rules.into_iter().any(|r| *r == MaskRule::Hash) {
+            return Ok(Self { key: None });
+        }
+        let key = key.ok_or_else(|| anyhow::anyhow!("--mask-key is required for hash masking"))?;
+        Ok(Self {
+            key: Some(KeyedHasher::load(key)?),
+        })
+    }
+
+    pub(crate) fn mask(&self, field: &str, rule: MaskRule) -> Result<String> {
+        match rule {
+            MaskRule::Hash => {
+                let key = self
+                    .key
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("--mask-key is required for hash masking"))?;
+                Ok(key.hash(field.as_bytes()).to_hex().to_string())
+            }
+            MaskRule::Partial { prefix, suffix } => Ok(mask_partial(field, prefix, suffix)),
+            MaskRule::Redact => Ok(REDACTED.to_string()),
+        }
+    }
+}
+
+/// 按字符保留前后部分；值太短时全部替换，避免泄露完整的原值
+fn mask_partial(field: &str, prefix: usize, suffix: usize) -> String {
+    let chars: Vec<char> = field.chars().collect();
+    if chars.len() <= prefix + suffix {
+        return "*".repeat(chars.len());
+    }
+    let mut masked: String = chars[..prefix].iter().collect();
+    masked.push_str(&"*".repeat(chars.len() - prefix - suffix));
+    masked.extend(&chars[chars.len() - suffix..]);
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masker() -> Result<()> {
+        let masker = Masker::new(
+            &[MaskRule::Hash, MaskRule::Redact],
+            Some("fixtures/blake3.txt"),
+        )?;
+        let a = masker.mask("alice@example.com", MaskRule::Hash)?;
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, masker.mask("alice@example.com", MaskRule::Hash)?);
+        assert_ne!(a, masker.mask("bob@example.com", MaskRule::Hash)?);
+        assert_eq!(masker.mask("alice", MaskRule::Redact)?, REDACTED);
+
+        let partial = MaskRule::Partial {
+            prefix: 3,
+            suffix: 4,
+        };
+        assert_eq!(masker.mask("13812345678", partial)?, "138****5678");
+        assert_eq!(
+            masker.mask(
+                "张三丰先生",
+                MaskRule::Partial {
+                    prefix: 1,
+                    suffix: 1
+                }
+            )?,
+            "张***生"
+        );
+        assert_eq!(masker.mask("123", partial)?, "***");
+
+        assert!(Masker::new(&[MaskRule::Hash], None).is_err());
+        // 未配置 hash 规则时没有加载密钥，返回错误而不是 panic
+        let masker = Masker::new(&[MaskRule::Redact], None)?;
+        assert!(masker.mask("alice", MaskRule::Hash).is_err());
+
+        let short = std::env::temp_dir().join("rcli_test_short_mask_key.txt");
+        std::fs::write(&short, b"too short")?;
+        let err = Masker::new(&[MaskRule::Hash], short.to_str())
+            .err()
+            .expect("short key is rejected");
+        assert!(err.to_string().contains("Invalid key: expect 32 bytes"));
+        std::fs::remove_file(short)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+use crate::cli::{CsvWriteOpts, OutputFormat, QuoteStyle};
+use anyhow::Result;
+use csv::WriterBuilder;
+use serde_json::{Map, Value};
+use std::io::Write;
+
+///
+/// 逐行写出转换结果，内存占用与输入大小无关
+///
+pub(crate) trait RowWriter {
+    /// 写出一行
+    fn write_row(&mut self, row: &Value) -> Result<()>;
+    /// 写出收尾内容（如 JSON 数组的 `]`）并 flush
+    fn finish(&mut self) -> Result<()>;
+}
+
+///
+/// 按照输出格式创建 RowWriter，headers 决定 csv/markdown 输出的列顺序
+///
+pub(crate) fn row_writer<'a, W: Write + 'a>(
+    writer: W,
+    headers: Vec<String>,
+    opts: &CsvWriteOpts,
+) -> Result<Box<dyn RowWriter + 'a>> {
+    let writer: Box<dyn RowWriter + 'a> = match opts.format {
+        OutputFormat::Json => Box::new(JsonWriter { writer, count: 0 }),
+        OutputFormat::Yaml => Box::new(YamlWriter { writer, count: 0 }),
+        OutputFormat::Toml => Box::new(TomlWriter { writer, count: 0 }),
+        OutputFormat::Ndjson => Box::new(NdjsonWriter { writer }),
+        OutputFormat::Csv => {
+            let mut writer = WriterBuilder::new()
+                .delimiter(opts.out_delimiter)
+                .quote_style(opts.quote.into())
+                .from_writer(writer);
+            writer.write_record(&headers)?;
+            Box::new(CsvWriter { writer, headers })
+        }
+        OutputFormat::Markdown => {
+            let mut writer = writer;
+            writer
+                .write_all(markdown_line(headers.iter().map(|h| escape_markdown(h))).as_bytes())?;
+            writer
+                .write_all(markdown_line(headers.iter().map(|_| "---".to_string())).as_bytes())?;
+            Box::new(MarkdownWriter { writer, headers })
+        }
+    };
+    Ok(writer)
+}
+
+///
+/// 单元格的文本表示：null 为空字符串，字符串原样输出，其余使用 JSON 表示
+///
+pub(crate) fn value_to_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// 增量输出 JSON 数组，格式与 serde_json::to_string_pretty 一致
+struct JsonWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+/// 每行输出为单元素的 YAML 序列，拼接后即为完整的序列
+struct YamlWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+/// 每行输出为一个 [[rows]] 表，拼接后即为 TOML 表数组
+struct TomlWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+struct NdjsonWriter<W> {
+    writer: W,
+}
+
+struct CsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+    headers: Vec<String>,
+}
+
+struct MarkdownWriter<W> {
+    writer: W,
+    headers: Vec<String>,
+}
+
+impl<W: Write> RowWriter for JsonWriter<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        let sep = if self.count == 0 { "[\n" } else { ",\n" };
+        let pretty = serde_json::to_string_pretty(row)?;
+        let indented = pretty
+            .lines()
+            .map(|l| format!("  {}", l))
+            .collect::<Vec<_>>();
+        write!(self.writer, "{}{}", sep, indented.join("\n"))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let end = if self.count == 0 { "[]" } else { "\n]" };
+        self.writer.write_all(end.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RowWriter for YamlWriter<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        serde_yaml::to_writer(&mut self.writer, &[row])?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.count == 0 {
+            self.writer.write_all(b"[]\n")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RowWriter for TomlWriter<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        // TOML 顶层必须是表，且不支持 null，因此输出为 [[rows]] 并去掉 null 字段
+        let mut doc = Map::new();
+        doc.insert("rows".to_string(), Value::Array(vec![strip_nulls(row)?]));
+        if self.count > 0 {
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer.write_all(toml::to_string(&doc)?.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RowWriter for NdjsonWriter<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, row)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RowWriter for CsvWriter<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        self.writer
+            .write_record(self.headers.iter().map(|h| value_to_field(&row[h])))?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RowWriter for MarkdownWriter<W> {
+    fn write_row(&mut self, row: &Value) -> Result<()> {
+        let line = markdown_line(
+            self.headers
+                .iter()
+                .map(|h| escape_markdown(&value_to_field(&row[h]))),
+        );
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// 去掉表中值为 null 的字段；数组中的 null 无法去掉（会改变下标），返回错误
+fn strip_nulls(value: &Value) -> Result<Value> {
+    Ok(match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| Ok((k.clone(), strip_nulls(v)?)))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Array(arr) => {
+            if arr.iter().any(Value::is_null) {
+                anyhow::bail!("TOML cannot represent null inside an array: {}", value);
+            }
+            Value::Array(arr.iter().map(strip_nulls).collect::<Result<_>>()?)
+        }
+        v => v.clone(),
+    })
+}
+
+fn markdown_line(cells: impl Iterator<Item = String>) -> String {
+    let cells: Vec<String> = cells.collect();
+    format!("| {} |\n", cells.join(" | "))
+}
+
+/// 转义 markdown 表格中的竖线，换行替换为 <br>
+fn escape_markdown(s: &str) -> String {
+    s.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+impl From<QuoteStyle> for csv::QuoteStyle {
+    fn from(quote: QuoteStyle) -> Self {
+        match quote {
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn serialize_rows(rows: &[Value], headers: &[String], opts: &CsvWriteOpts) -> Result<String> {
+        let mut buf = Vec::new();
+        let mut writer = row_writer(&mut buf, headers.to_vec(), opts)?;
+        for row in rows {
+            writer.write_row(row)?;
+        }
+        writer.finish()?;
+        drop(writer);
+        Ok(String::from_utf8(buf)?)
+    }
+
+    fn rows() -> (Vec<Value>, Vec<String>) {
+        let rows = vec![
+            json!({"name": "a|b", "count": 1, "note": null}),
+            json!({"name": "c", "count": 2, "note": "x;y"}),
+        ];
+        let headers = vec!["name".to_string(), "count".to_string(), "note".to_string()];
+        (rows, headers)
+    }
+
+    #[test]
+    fn test_serialize_ndjson_and_markdown() -> Result<()> {
+        let (rows, headers) = rows();
+        let opts = CsvWriteOpts {
+            format: OutputFormat::Ndjson,
+            ..Default::default()
+        };
+        let s = serialize_rows(&rows, &headers, &opts)?;
+        assert_eq!(s.lines().count(), 2);
+
+        let opts = CsvWriteOpts {
+            format: OutputFormat::Markdown,
+            ..Default::default()
+        };
+        let s = serialize_rows(&rows, &headers, &opts)?;
+        assert_eq!(
+            s,
+            "| name | count | note |\n| --- | --- | --- |\n| a\\|b | 1 |  |\n| c | 2 | x;y |\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_csv_and_toml() -> Result<()> {
+        let (rows, headers) = rows();
+        let opts = CsvWriteOpts {
+            format: OutputFormat::Csv,
+            out_delimiter: b';',
+            quote: QuoteStyle::Necessary,
+        };
+        let s = serialize_rows(&rows, &headers, &opts)?;
+        assert_eq!(s, "name;count;note\na|b;1;\nc;2;\"x;y\"\n");
+
+        let opts = CsvWriteOpts {
+            format: OutputFormat::Toml,
+            ..Default::default()
+        };
+        let s = serialize_rows(&rows, &headers, &opts)?;
+        assert_eq!(s.matches("[[rows]]").count(), 2);
+        assert!(!s.contains("note = \"\""));
+        assert_eq!(
+            toml::from_str::<toml::Table>(&s)?["rows"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // 表中的 null 去掉，数组中的 null 报错而不是改变下标
+        let nested = json!({"a": {"b": null, "c": [1, [2, 3]]}});
+        assert_eq!(strip_nulls(&nested)?, json!({"a": {"c": [1, [2, 3]]}}));
+        let sparse = json!({"tags": ["x", null, "z"]});
+        assert!(serialize_rows(&[sparse], &["tags".to_string()], &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_streaming_matches_collected() -> Result<()> {
+        let (rows, headers) = rows();
+        for format in [OutputFormat::Json, OutputFormat::Yaml] {
+            let opts = CsvWriteOpts {
+                format,
+                ..Default::default()
+            };
+            let expected = match format {
+                OutputFormat::Json => serde_json::to_string_pretty(&rows)?,
+                _ => serde_yaml::to_string(&rows)?,
+            };
+            assert_eq!(serialize_rows(&rows, &headers, &opts)?, expected);
+            let empty = match format {
+                OutputFormat::Json => serde_json::to_string_pretty(&Vec::<Value>::new())?,
+                _ => serde_yaml::to_string(&Vec::<Value>::new())?,
+            };
+            assert_eq!(serialize_rows(&[], &headers, &opts)?, empty);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use super::{
+    csv_convert::build_reader,
+    csv_errors::RowErrors,
+    csv_filter::truthy,
+    csv_output::row_writer,
+    csv_sql::{cmp_values, Expr, Query, SelectItem},
+    csv_types::infer_value,
+};
+use crate::{cli::CsvQueryOpts, get_writer};
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+///
+/// 在内存中对一个或多个csv文件执行 SQL 查询（语法见 Query），结果按 OutputFormat 输出
+///
+/// 表名通过 --table name=path 指定，未指定时依次尝试 name 和 name.csv 作为文件路径。
+/// 单元格按 --infer 的规则推断类型，因此 `Position = 1` 按数字比较
+///
+pub fn process_csv_query(opts: &CsvQueryOpts) -> Result<()> {
+    let mut query = Query::parse(&opts.query)?;
+
+    // 加载 FROM 和 JOIN 的表，同时构建列的作用域
+    let mut scope = Vec::new();
+    let mut rows = load_table(opts, &query.from.name, &query.from.alias, &mut scope)?;
+    for join in &mut query.joins {
+        let width = scope.len();
+        let right = load_table(opts, &join.table.name, &join.table.alias, &mut scope)?;
+        join.on.bind(&scope).context("in JOIN ... ON")?;
+        if join.on.has_aggregate() {
+            anyhow::bail!("aggregate functions are not allowed in JOIN ... ON");
+        }
+        let right_width = scope.len() - width;
+        let mut joined = Vec::new();
+        for left in rows {
+            let mut matched = false;
+            for r in &right {
+                let row: Vec<Value> = left.iter().chain(r.iter()).cloned().collect();
+                if truthy(&join.on.eval(&[&row])) {
+                    joined.push(row);
+                    matched = true;
+                }
+            }
+            if !matched && join.left {
+                let mut row = left;
+                row.resize(width + right_width, Value::Null);
+                joined.push(row);
+            }
+        }
+        rows = joined;
+    }
+
+    if let Some(filter) = &mut query.filter {
+        filter.bind(&scope).context("in WHERE")?;
+        if filter.has_aggregate() {
+            anyhow::bail!("aggregate functions are not allowed in WHERE, use HAVING instead");
+        }
+        rows.retain(|row| truthy(&filter.eval(&[row])));
+    }
+
+    // 输出列：* 展开为全部列，列名重复时加上序号
+    let mut names = Vec::new();
+    for item in &mut query.select {
+        match item {
+            SelectItem::Wildcard => names.extend(scope.iter().map(|(_, name)| name.clone())),
+            SelectItem::Expr { expr, name } => {
+                expr.bind(&scope)?;
+                names.push(name.clone());
+            }
+        }
+    }
+    let headers = unique_names(names.iter().map(|name| name.as_str()));
+    for (expr, _) in &mut query.order_by {
+        if output_column(expr, &names)?.is_none() {
+            expr.bind(&scope).context("in ORDER BY")?;
+        }
+    }
+    for expr in &mut query.group_by {
+        expr.bind(&scope).context("in GROUP BY")?;
+    }
+    if let Some(having) = &mut query.having {
+        having.bind(&scope).context("in HAVING")?;
+    }
+    let order_by = query
+        .order_by
+        .iter()
+        .map(|(expr, desc)| {
+            let key = match output_column(expr, &names)? {
+                Some(i) => OrderKey::Output(i),
+                None => OrderKey::Expr(expr),
+            };
+            Ok((key, *desc))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // 每个结果行对应的一组输入行：普通查询为单行，聚合查询为一个分组
+    let groups: Vec<Vec<&[Value]>> = if query.is_aggregate() {
+        let mut groups: Vec<Vec<&[Value]>> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for row in &rows {
+            let key: Vec<Value> = query.group_by.iter().map(|e| e.eval(&[row])).collect();
+            let key = Value::Array(key).to_string();
+            let i = *index.entry(key).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[i].push(row.as_slice());
+        }
+        // 没有 GROUP BY 时即使没有输入行也输出一行，如 COUNT(*) 为 0
+        if groups.is_empty() && query.group_by.is_empty() {
+            groups.push(Vec::new());
+        }
+        if let Some(having) = &query.having {
+            groups.retain(|group| truthy(&having.eval(group)));
+        }
+        groups
+    } else {
+        rows.iter().map(|row| vec![row.as_slice()]).collect()
+    };
+
+    let mut results: Vec<(Vec<Value>, Vec<Value>)> = Vec::with_capacity(groups.len());
+    let mut seen = HashSet::new();
+    for group in &groups {
+        let mut values = Vec::with_capacity(headers.len());
+        for item in &query.select {
+            match item {
+                SelectItem::Wildcard => match group.first() {
+                    Some(row) => values.extend(row.iter().cloned()),
+                    None => values.extend(scope.iter().map(|_| Value::Null)),
+                },
+                SelectItem::Expr { expr, .. } => values.push(expr.eval(group)),
+            }
+        }
+        if query.distinct && !seen.insert(Value::Array(values.clone()).to_string()) {
+            continue;
+        }
+        let keys = order_by
+            .iter()
+            .map(|(key, _)| match key {
+                OrderKey::Output(i) => values[*i].clone(),
+                OrderKey::Expr(expr) => expr.eval(group),
+            })
+            .collect();
+        results.push((keys, values));
+    }
+    if !order_by.is_empty() {
+        results.sort_by(|(a, _), (b, _)| {
+            a.iter()
+                .zip(b)
+                .zip(&order_by)
+                .map(|((x, y), (_, desc))| {
+                    let ord = cmp_values(x, y);
+                    if *desc {
+                        ord.reverse()
+                    } else {
+                        ord
+                    }
+                })
+                .find(|ord| *ord != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    let mut writer = row_writer(get_writer(&opts.output)?, headers.clone(), &opts.write)?;
+    let limit = query.limit.unwrap_or(usize::MAX);
+    for (_, values) in results.into_iter().skip(query.offset).take(limit) {
+        let row: Map<String, Value> = headers.iter().cloned().zip(values).collect();
+        writer.write_row(&Value::Object(row))?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+enum OrderKey<'a> {
+    Output(usize),
+    Expr(&'a Expr),
+}
+
+/// ORDER BY 中的输出列名或从 1 开始的输出列序号，返回输出列的位置
+fn output_column(expr: &Expr, names: &[String]) -> Result<Option<usize>> {
+    match expr {
+        Expr::Literal(Value::Number(n)) => match n.as_u64() {
+            Some(i) if i >= 1 && i as usize <= names.len() => Ok(Some(i as usize - 1)),
+            _ => anyhow::bail!("ORDER BY position {} is out of range", n),
+        },
+        Expr::Column {
+            table: None, name, ..
+        } => Ok(names.iter().position(|n| n == name)),
+        _ => Ok(None),
+    }
+}
+
+/// 读取表的全部行并推断类型，将 (别名, 列名) 追加到作用域中
+fn load_table(
+    opts: &CsvQueryOpts,
+    name: &str,
+    alias: &str,
+    scope: &mut Vec<(String, String)>,
+) -> Result<Vec<Vec<Value>>> {
+    let path = resolve_table(opts, name)?;
+    let (mut reader, headers) = build_reader(&path, &opts.read)?;
+    if scope.iter().any(|(t, _)| t == alias) {
+        anyhow::bail!("table alias {:?} is used more than once", alias);
+    }
+    scope.extend(headers.iter().map(|h| (alias.to_string(), h.to_string())));
+
+    let mut rows = Vec::new();
+    let mut errors = RowErrors::new(&opts.read)?;
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        let row = (0..headers.len())
+            .map(|i| record.get(i).map_or(Value::Null, infer_value))
+            .collect();
+        rows.push(row);
+    }
+    errors.finish()?;
+    Ok(rows)
+}
+
+fn resolve_table(opts: &CsvQueryOpts, name: &str) -> Result<String> {
+    if let Some((_, path)) = opts.tables.iter().find(|(table, _)| table == name) {
+        return Ok(path.clone());
+    }
+    [name.to_string(), format!("{}.csv", name)]
+        .into_iter()
+        .find(|path| Path::new(path).is_file())
+        .ok_or_else(|| anyhow::anyhow!("unknown table {:?}, use --table {}=<path>", name, name))
+}
+
+/// 重复的列名依次加上 _2, _3 ...
+fn unique_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for name in names {
+        let mut unique = name.to_string();
+        let mut n = 1;
+        while out.contains(&unique) {
+            n += 1;
+            unique = format!("{}_{}", name, n);
+        }
+        out.push(unique);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{CsvReadOpts, CsvWriteOpts, OutputFormat};
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    fn query(sql: &str) -> Result<Vec<Value>> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNT.fetch_add(1, AtomicOrdering::SeqCst);
+        let output = std::env::temp_dir().join(format!("rcli_test_query_{}.ndjson", n));
+        let opts = CsvQueryOpts {
+            query: sql.to_string(),
+            tables: vec![
+                ("users".to_string(), "fixtures/join_left.csv".to_string()),
+                ("scores".to_string(), "fixtures/join_right.csv".to_string()),
+            ],
+            output: output.to_str().unwrap().to_string(),
+            read: CsvReadOpts::default(),
+            write: CsvWriteOpts {
+                format: OutputFormat::Ndjson,
+                ..Default::default()
+            },
+        };
+        process_csv_query(&opts)?;
+        let rows = std::fs::read_to_string(&output)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Value>, _>>()?;
+        std::fs::remove_file(output)?;
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_process_csv_query() -> Result<()> {
+        let rows = query(
+            r#"SELECT Title, "Read Count" FROM zcyj WHERE Position = 1 ORDER BY "Like Count" DESC LIMIT 3"#,
+        )?;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0]["Read Count"], 100001);
+        assert_eq!(rows[0].as_object().unwrap().len(), 2);
+
+        let rows = query("SELECT Position, COUNT(*) AS n, MAX(\"Read Count\") FROM zcyj GROUP BY Position ORDER BY 1")?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["Position"], 1);
+        assert_eq!(rows[0]["n"], 180);
+        assert_eq!(rows[1]["n"], 159);
+
+        let rows = query("SELECT u.name, s.score FROM users u LEFT JOIN scores s ON u.id = s.user_id ORDER BY s.score DESC, u.name")?;
+        let pairs: Vec<(String, Value)> = rows
+            .iter()
+            .map(|r| (r["name"].as_str().unwrap().to_string(), r["score"].clone()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("alice".to_string(), Value::from(95)),
+                ("alice".to_string(), Value::from(90)),
+                ("bob".to_string(), Value::Null),
+                ("carol".to_string(), Value::Null),
+            ]
+        );
+
+        let rows = query("SELECT * FROM users JOIN scores ON id = user_id WHERE score > 90")?;
+        assert_eq!(
+            rows,
+            vec![
+                serde_json::json!({"id": 1, "name": "alice", "user_id": 1, "score": 95, "name_2": "A2"})
+            ]
+        );
+
+        let rows = query("SELECT COUNT(*) FROM users WHERE id > 10")?;
+        assert_eq!(rows, vec![serde_json::json!({"COUNT(*)": 0})]);
+
+        let rows = query("SELECT DISTINCT user_id FROM scores")?;
+        assert_eq!(rows.len(), 2);
+
+        let rows = query(
+            "SELECT user_id, SUM(score) AS total FROM scores GROUP BY user_id HAVING COUNT(*) > 1",
+        )?;
+        assert_eq!(rows, vec![serde_json::json!({"user_id": 1, "total": 185})]);
+
+        assert!(query("SELECT nope FROM users").is_err());
+        assert!(query("SELECT name FROM users JOIN scores ON id = user_id").is_err());
+        assert!(query("SELECT * FROM missing_table").is_err());
+        Ok(())
+    }
+}
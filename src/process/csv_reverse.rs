@@ -0,0 +1,325 @@
+use super::csv_output::row_writer;
+use crate::{
+    cli::{CsvWriteOpts, InputFormat, OutputFormat},
+    get_reader, get_writer,
+};
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, Read},
+};
+
+///
+/// 将 json/yaml/ndjson 中的对象列表转换为csv
+///
+/// 表头为所有对象字段的并集（按首次出现的顺序），嵌套对象按 a.b 展开，数组按 a[0] 展开
+///
+pub fn process_to_csv(
+    input: &str,
+    output: String,
+    from: InputFormat,
+    write: &CsvWriteOpts,
+) -> Result<()> {
+    let reader = get_reader(input)?;
+    let items = read_items(reader, from)?;
+
+    let mut rows = Vec::with_capacity(items.len());
+    let mut headers = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, item) in items.iter().enumerate() {
+        if !item.is_object() {
+            anyhow::bail!("item {} is not an object: {}", i, item);
+        }
+        let mut row = Map::new();
+        flatten_value("", item, &mut row).map_err(|e| anyhow::anyhow!("item {}: {}", i, e))?;
+        for key in row.keys() {
+            if seen.insert(key.clone()) {
+                headers.push(key.clone());
+            }
+        }
+        rows.push(Value::Object(row));
+    }
+
+    let write = CsvWriteOpts {
+        format: OutputFormat::Csv,
+        ..write.clone()
+    };
+    let mut writer = row_writer(get_writer(&output)?, headers, &write)?;
+    for row in &rows {
+        writer.write_row(row)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// 读取输入中的所有对象：数组展开为多项，单个对象视为一项
+fn read_items(reader: Box<dyn Read>, from: InputFormat) -> Result<Vec<Value>> {
+    let value = match from {
+        InputFormat::Json => serde_json::from_reader(reader)?,
+        InputFormat::Yaml => serde_yaml::from_reader(reader)?,
+        InputFormat::Ndjson => {
+            let mut items = Vec::new();
+            for (i, line) in BufReader::new(reader).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let item = serde_json::from_str(&line)
+                    .map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e))?;
+                items.push(item);
+            }
+            Value::Array(items)
+        }
+        InputFormat::Csv => anyhow::bail!("csv input does not need reverse conversion"),
+    };
+    let items = match value {
+        Value::Array(items) => items,
+        value => vec![value],
+    };
+    Ok(items)
+}
+
+///
+/// 展开嵌套结构：对象字段使用 `.` 连接，数组元素使用 `[i]`，空对象/空数组保持原值
+///
+/// 不同路径展开为同一列名时（如字段 "a.b" 与嵌套的 {"a": {"b": ...}}）返回错误，而不是互相覆盖
+///
+pub(crate) fn flatten_value(
+    prefix: &str,
+    value: &Value,
+    out: &mut Map<String, Value>,
+) -> Result<()> {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", prefix, k)
+                };
+                flatten_value(&key, v, out)?;
+            }
+        }
+        Value::Array(arr) if !arr.is_empty() => {
+            for (i, v) in arr.iter().enumerate() {
+                flatten_value(&format!("{}[{}]", prefix, i), v, out)?;
+            }
+        }
+        v => {
+            if out.insert(prefix.to_string(), v.clone()).is_some() {
+                anyhow::bail!("column {:?} appears more than once when flattening", prefix);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 列名中允许的最大数组下标，避免 `tags[99999999999]` 这类列名导致巨量内存分配
+const MAX_ARRAY_INDEX: usize = 10_000;
+
+///
+/// flatten_value 的逆操作：按列名中的 `.` 和 `[i]` 将扁平的行还原为嵌套的对象和数组
+///
+pub(crate) struct Unflattener {
+    paths: Vec<(String, Vec<Segment>)>,
+}
+
+/// 列名路径中的一段：对象字段或数组下标
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// 路径上某一位置的值的种类，用于检查列名之间是否冲突
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    Leaf,
+    Object,
+    Array,
+}
+
+impl Unflattener {
+    /// 解析所有列名，同一路径既是值又是对象/数组（如 a 和 a.b），或既是对象又是数组时返回错误
+    pub(crate) fn new(headers: &[String]) -> Result<Self> {
+        let paths: Vec<(String, Vec<Segment>)> =
+            headers.iter().map(|h| (h.clone(), parse_path(h))).collect();
+        let mut nodes: HashMap<&[Segment], Node> = HashMap::new();
+        for (header, path) in &paths {
+            if path
+                .iter()
+                .any(|s| matches!(s, Segment::Index(i) if *i > MAX_ARRAY_INDEX))
+            {
+                anyhow::bail!(
+                    "column {:?} has an array index larger than {}",
+                    header,
+                    MAX_ARRAY_INDEX
+                );
+            }
+            for i in 1..=path.len() {
+                let node = match path.get(i) {
+                    None => Node::Leaf,
+                    Some(Segment::Key(_)) => Node::Object,
+                    Some(Segment::Index(_)) => Node::Array,
+                };
+                match nodes.insert(&path[..i], node) {
+                    Some(prev) if prev != node || node == Node::Leaf => {
+                        anyhow::bail!(
+                            "column {:?} conflicts with another column when unflattening",
+                            header
+                        )
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Self { paths })
+    }
+
+    /// 将 {列名: 值} 还原为嵌套结构，数组中缺少的下标填充 null
+    pub(crate) fn unflatten(&self, row: &Value) -> Value {
+        let mut out = Value::Object(Map::new());
+        for (header, path) in &self.paths {
+            let value = row.get(header).cloned().unwrap_or(Value::Null);
+            insert_path(&mut out, path, value);
+        }
+        out
+    }
+}
+
+/// 解析列名，如 `user.tags[0]` -> [Key(user), Key(tags), Index(0)]；无法解析的部分按普通字段名处理
+fn parse_path(header: &str) -> Vec<Segment> {
+    let mut path = Vec::new();
+    for part in header.split('.') {
+        let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        let mut indices = Vec::new();
+        while let Some((index, r)) = rest
+            .strip_prefix('[')
+            .and_then(|r| r.split_once(']'))
+            .and_then(|(n, r)| Some((n.parse::<usize>().ok()?, r)))
+        {
+            indices.push(index);
+            rest = r;
+        }
+        // 顶层必须是对象字段；下标后还有多余字符时整段视为字段名
+        if !rest.is_empty() || (name.is_empty() && path.is_empty()) {
+            path.push(Segment::Key(part.to_string()));
+            continue;
+        }
+        path.push(Segment::Key(name.to_string()));
+        path.extend(indices.into_iter().map(Segment::Index));
+    }
+    path
+}
+
+/// 沿路径创建对象/数组并写入值，路径冲突已在 Unflattener::new 中排除
+fn insert_path(target: &mut Value, path: &[Segment], value: Value) {
+    let Some((first, rest)) = path.split_first() else {
+        *target = value;
+        return;
+    };
+    let slot = match first {
+        Segment::Key(key) => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+            let map = target.as_object_mut().expect("target is an object");
+            map.entry(key.clone()).or_insert(Value::Null)
+        }
+        Segment::Index(index) => {
+            if !target.is_array() {
+                *target = Value::Array(Vec::new());
+            }
+            let arr = target.as_array_mut().expect("target is an array");
+            if arr.len() <= *index {
+                arr.resize(index + 1, Value::Null);
+            }
+            &mut arr[*index]
+        }
+    };
+    insert_path(slot, rest, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten_value() -> Result<()> {
+        let mut out = Map::new();
+        let v = json!({"id": 1, "user": {"name": "a", "tags": ["x", "y"]}, "extra": {}});
+        flatten_value("", &v, &mut out)?;
+        let keys: Vec<&str> = out.keys().map(|k| k.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec!["id", "user.name", "user.tags[0]", "user.tags[1]", "extra"]
+        );
+        assert_eq!(out["user.tags[1]"], json!("y"));
+        assert_eq!(out["extra"], json!({}));
+
+        for v in [
+            json!({"a.b": 1, "a": {"b": 2}}),
+            json!({"a[0]": 1, "a": [2]}),
+        ] {
+            assert!(flatten_value("", &v, &mut Map::new()).is_err(), "{}", v);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_items() -> Result<()> {
+        let ndjson = "{\"a\": 1}\n\n{\"b\": 2}\n";
+        let items = read_items(Box::new(ndjson.as_bytes()), InputFormat::Ndjson)?;
+        assert_eq!(items, vec![json!({"a": 1}), json!({"b": 2})]);
+
+        let yaml = "- a: 1\n- a: 2\n  b: x\n";
+        let items = read_items(Box::new(yaml.as_bytes()), InputFormat::Yaml)?;
+        assert_eq!(items[1], json!({"a": 2, "b": "x"}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unflatten() -> Result<()> {
+        let headers: Vec<String> = [
+            "id",
+            "user.name",
+            "user.tags[0]",
+            "user.tags[2]",
+            "m[0][1]",
+            "a.[x]",
+        ]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+        let unflattener = Unflattener::new(&headers)?;
+        let row = json!({"id": 1, "user.name": "a", "user.tags[0]": "x", "user.tags[2]": "z", "m[0][1]": 5, "a.[x]": null});
+        assert_eq!(
+            unflattener.unflatten(&row),
+            json!({"id": 1, "user": {"name": "a", "tags": ["x", null, "z"]}, "m": [[null, 5]], "a": {"[x]": null}})
+        );
+
+        // 与 flatten_value 互为逆操作
+        let v = json!({"id": 1, "user": {"name": "a", "tags": ["x", "y"]}});
+        let mut flat = Map::new();
+        flatten_value("", &v, &mut flat)?;
+        let headers: Vec<String> = flat.keys().cloned().collect();
+        assert_eq!(
+            Unflattener::new(&headers)?.unflatten(&Value::Object(flat)),
+            v
+        );
+
+        for conflict in [vec!["a", "a.b"], vec!["a.b", "a[0]"], vec!["a", "a"]] {
+            let headers: Vec<String> = conflict.iter().map(|h| h.to_string()).collect();
+            assert!(Unflattener::new(&headers).is_err(), "{:?}", conflict);
+        }
+
+        for index in [MAX_ARRAY_INDEX + 1, usize::MAX] {
+            let headers = vec![format!("tags[{}]", index)];
+            assert!(Unflattener::new(&headers).is_err(), "{}", index);
+        }
+        assert!(Unflattener::new(&[format!("tags[{}]", MAX_ARRAY_INDEX)]).is_ok());
+        Ok(())
+    }
+}
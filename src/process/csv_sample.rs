@@ -0,0 +1,168 @@
+use super::{
+    csv_convert::{build_reader, resolve_column, RowBuilder},
+    csv_errors::RowErrors,
+    csv_output::row_writer,
+};
+use crate::{
+    cli::{CsvConvertOpts, CsvSampleOpts},
+    get_writer,
+};
+use anyhow::Result;
+use csv::StringRecord;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+
+///
+/// 蓄水池抽样：一次遍历从任意长度的输入中等概率抽取最多 n 行，内存占用与 n 成正比
+///
+/// 指定 --stratify 时每组各抽取 n 行。抽中的行按原文件中的顺序输出，
+/// 指定 --seed 时结果可复现：随机数流固定为 ChaCha8，下标由 random_index 自行映射，
+/// 不依赖 rand 的均匀分布实现
+///
+pub fn process_csv_sample(opts: &CsvSampleOpts) -> Result<()> {
+    let (mut reader, headers) = build_reader(&opts.input, &opts.read)?;
+    let stratify = opts
+        .stratify
+        .as_deref()
+        .map(|spec| resolve_column(&headers, spec))
+        .transpose()?;
+    let builder = RowBuilder::new(headers, &CsvConvertOpts::default())?;
+    let mut rng = match opts.seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+
+    let mut reservoirs: HashMap<String, Reservoir> = HashMap::new();
+    let mut errors = RowErrors::new(&opts.read)?;
+    for (i, result) in reader.records().enumerate() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        let key = stratify.map_or("", |col| record.get(col).unwrap_or_default());
+        // 避免每行都分配分组键
+        let reservoir = match reservoirs.get_mut(key) {
+            Some(reservoir) => reservoir,
+            None => reservoirs.entry(key.to_string()).or_default(),
+        };
+        reservoir.offer(i, record, opts.rows, &mut rng);
+    }
+    errors.finish()?;
+
+    let mut samples: Vec<(usize, StringRecord)> =
+        reservoirs.into_values().flat_map(|r| r.rows).collect();
+    samples.sort_by_key(|(i, _)| *i);
+    let mut writer = row_writer(get_writer(&opts.output)?, builder.headers(), &opts.write)?;
+    for (_, record) in &samples {
+        writer.write_row(&builder.build(record)?)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+///
+/// 单组的蓄水池（Algorithm R），保存抽中的行及其在输入中的序号
+///
+#[derive(Default)]
+struct Reservoir {
+    seen: usize,
+    rows: Vec<(usize, StringRecord)>,
+}
+
+impl Reservoir {
+    /// 第 k 行（从 0 开始）以 n/(k+1) 的概率替换蓄水池中随机的一行
+    fn offer(&mut self, index: usize, record: StringRecord, n: usize, rng: &mut impl RngCore) {
+        if self.rows.len() < n {
+            self.rows.push((index, record));
+        } else {
+            let j = random_index(rng, self.seen + 1);
+            if j < n {
+                self.rows[j] = (index, record);
+            }
+        }
+        self.seen += 1;
+    }
+}
+
+/// 将 64 位随机数按乘法移位映射到 [0, n)，偏差不超过 n / 2^64
+fn random_index(rng: &mut impl RngCore, n: usize) -> usize {
+    ((rng.next_u64() as u128 * n as u128) >> 64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{CsvReadOpts, CsvWriteOpts, OutputFormat};
+    use serde_json::Value;
+
+    fn sample(rows: usize, seed: u64, stratify: Option<&str>) -> Result<Vec<Value>> {
+        let output =
+            std::env::temp_dir().join(format!("rcli_test_sample_{}_{}.ndjson", rows, seed));
+        process_csv_sample(&CsvSampleOpts {
+            input: "zcyj.csv".to_string(),
+            output: output.to_str().unwrap().to_string(),
+            rows,
+            seed: Some(seed),
+            stratify: stratify.map(|s| s.to_string()),
+            read: CsvReadOpts::default(),
+            write: CsvWriteOpts {
+                format: OutputFormat::Ndjson,
+                ..Default::default()
+            },
+        })?;
+        let rows = std::fs::read_to_string(&output)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Value>, _>>()?;
+        std::fs::remove_file(output)?;
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_process_csv_sample() -> Result<()> {
+        let a = sample(10, 42, None)?;
+        assert_eq!(a.len(), 10);
+        // 固定种子的抽样结果，依赖升级导致的任何漂移都会使该断言失败
+        let picked: Vec<(&str, &str)> = a
+            .iter()
+            .map(|r| {
+                (
+                    r["Publish Time"].as_str().unwrap(),
+                    r["Position"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            picked,
+            [
+                ("2024-04-06 20:23:00", "1"),
+                ("2024-06-01 20:14:00", "1"),
+                ("2024-04-21 19:47:00", "1"),
+                ("2024-04-07 19:47:00", "1"),
+                ("2024-04-27 12:05:32", "2"),
+                ("2024-02-09 19:40:00", "1"),
+                ("2024-03-17 19:21:00", "1"),
+                ("2024-04-15 21:22:00", "2"),
+                ("2024-01-29 19:36:00", "2"),
+                ("2024-05-19 19:42:00", "2"),
+            ]
+        );
+        assert_eq!(a, sample(10, 42, None)?);
+        assert_ne!(a, sample(10, 7, None)?);
+        assert_eq!(sample(1000, 42, None)?.len(), 339);
+
+        let rows = sample(2, 42, Some("Position"))?;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for row in &rows {
+            *counts
+                .entry(row["Position"].as_str().unwrap().to_string())
+                .or_default() += 1;
+        }
+        assert!(counts.len() > 1 && counts.values().all(|&c| c <= 2));
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use super::{csv_convert::build_reader, csv_errors::RowErrors};
+use crate::cli::CsvReadOpts;
+use anyhow::Result;
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Write},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+///
+/// 在终端中以对齐的表格显示csv内容，按显示宽度对齐（中文等宽字符占两列）
+///
+/// - head / tail：只显示前 N 行 / 后 N 行，同时指定时中间用 … 分隔
+/// - max_width：单元格最大显示宽度，超出部分截断并以 … 结尾
+///
+/// 输出为纯文本，适合通过管道交给 less 等分页工具；下游提前关闭管道时直接返回
+///
+pub fn process_csv_show(
+    input: &str,
+    opts: &CsvReadOpts,
+    head: Option<usize>,
+    tail: Option<usize>,
+    max_width: usize,
+    writer: &mut dyn Write,
+) -> Result<()> {
+    let (mut reader, headers) = build_reader(input, opts)?;
+    let mut first = Vec::new();
+    let mut last = VecDeque::new();
+    let mut total = 0;
+    let mut complete = true;
+    let mut errors = RowErrors::new(opts)?;
+    for result in reader.records() {
+        // 只显示前 N 行时不必读完整个文件
+        if tail.is_none() && head.is_some_and(|h| first.len() >= h) {
+            complete = false;
+            break;
+        }
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        total += 1;
+        let row: Vec<String> = record
+            .iter()
+            .map(|f| truncate_to_width(f, max_width))
+            .collect();
+        match (head, tail) {
+            (None, None) => first.push(row),
+            (Some(h), _) if first.len() < h => first.push(row),
+            (_, Some(t)) if t > 0 => {
+                if last.len() == t {
+                    last.pop_front();
+                }
+                last.push_back(row);
+            }
+            _ => {}
+        }
+    }
+
+    errors.finish()?;
+
+    let headers: Vec<String> = headers
+        .iter()
+        .map(|h| truncate_to_width(h, max_width))
+        .collect();
+    let skipped = total - first.len() - last.len();
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.width()).collect();
+    for row in first.iter().chain(last.iter()) {
+        for (i, cell) in row.iter().enumerate() {
+            if i < widths.len() {
+                widths[i] = widths[i].max(cell.width());
+            }
+        }
+    }
+
+    let table = Table { widths: &widths };
+    let result = (|| -> std::io::Result<()> {
+        writeln!(writer, "{}", table.border('┌', '┬', '┐'))?;
+        writeln!(writer, "{}", table.row(&headers))?;
+        writeln!(writer, "{}", table.border('├', '┼', '┤'))?;
+        for row in &first {
+            writeln!(writer, "{}", table.row(row))?;
+        }
+        if skipped > 0 && !last.is_empty() {
+            let gap: Vec<String> = widths.iter().map(|_| "…".to_string()).collect();
+            writeln!(writer, "{}", table.row(&gap))?;
+        }
+        for row in &last {
+            writeln!(writer, "{}", table.row(row))?;
+        }
+        writeln!(writer, "{}", table.border('└', '┴', '┘'))?;
+        if complete {
+            writeln!(writer, "{} rows", total)?;
+        } else {
+            writeln!(writer, "first {} rows", total)?;
+        }
+        writer.flush()
+    })();
+    match result {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        r => Ok(r?),
+    }
+}
+
+struct Table<'a> {
+    widths: &'a [usize],
+}
+
+impl Table<'_> {
+    fn border(&self, left: char, mid: char, right: char) -> String {
+        let lines: Vec<String> = self.widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{}{}{}", left, lines.join(&mid.to_string()), right)
+    }
+
+    fn row(&self, cells: &[String]) -> String {
+        let cells: Vec<String> = self
+            .widths
+            .iter()
+            .enumerate()
+            .map(|(i, w)| pad_to_width(cells.get(i).map(|c| c.as_str()).unwrap_or_default(), *w))
+            .collect();
+        format!("│ {} │", cells.join(" │ "))
+    }
+}
+
+///
+/// 将字符串截断到指定的显示宽度，超出时以 … 结尾；换行、制表符替换为空格
+///
+pub(crate) fn truncate_to_width(s: &str, max_width: usize) -> String {
+    let s: String = s
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if s.width() <= max_width {
+        return s;
+    }
+    let mut out = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        // 预留 1 列给 …
+        if width + w + 1 > max_width {
+            break;
+        }
+        width += w;
+        out.push(c);
+    }
+    out.push('…');
+    out
+}
+
+/// 按显示宽度在右侧补齐空格
+pub(crate) fn pad_to_width(s: &str, width: usize) -> String {
+    let pad = width.saturating_sub(s.width());
+    format!("{}{}", s, " ".repeat(pad))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("hello", 10), "hello");
+        assert_eq!(truncate_to_width("hello world", 6), "hello…");
+        assert_eq!(truncate_to_width("当上中层才知道", 7), "当上中…");
+        assert_eq!(truncate_to_width("a\nb", 10), "a b");
+        assert_eq!(pad_to_width("中文", 6), "中文  ");
+    }
+
+    #[test]
+    fn test_process_csv_show_head_tail() -> Result<()> {
+        let mut buf = Vec::new();
+        process_csv_show(
+            "zcyj.csv",
+            &CsvReadOpts::default(),
+            Some(2),
+            Some(1),
+            20,
+            &mut buf,
+        )?;
+        let out = String::from_utf8(buf)?;
+        let lines: Vec<&str> = out.lines().collect();
+        // 上边框、表头、分隔线、2 行 head、省略行、1 行 tail、下边框、总数
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[8], "339 rows");
+        let widths: Vec<usize> = lines[..8].iter().map(|l| l.width()).collect();
+        assert!(widths.iter().all(|w| *w == widths[0]), "{:?}", widths);
+
+        let mut buf = Vec::new();
+        process_csv_show(
+            "zcyj.csv",
+            &CsvReadOpts::default(),
+            Some(3),
+            None,
+            20,
+            &mut buf,
+        )?;
+        assert!(String::from_utf8(buf)?.ends_with("first 3 rows\n"));
+        Ok(())
+    }
+}
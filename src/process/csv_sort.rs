@@ -0,0 +1,497 @@
+use super::{
+    csv_convert::{build_reader, resolve_column},
+    csv_errors::RowErrors,
+    csv_types::parse_timestamp,
+};
+use crate::{
+    cli::{CsvReadOpts, SortKey, SortKind},
+    get_writer,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use csv::{ReaderBuilder, StringRecord, Writer, WriterBuilder};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashSet},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// 一次归并同时打开的临时文件数上限，超出时分多轮归并
+const MAX_MERGE_FANIN: usize = 64;
+
+///
+/// 按指定列排序csv，并可按列去重，输出为csv
+///
+/// 数据超出 memory_limit 时，将已排序的数据块写入 temp_dir 下的临时文件，最后多路归并输出，
+/// 因此可以处理大于内存的文件。排序是稳定的，相同键的行保持输入顺序。
+///
+/// dedup_by 按列的取值去重，保留排序后第一次出现的行；只记录键的哈希，内存占用与不同键的个数成正比
+///
+pub fn process_csv_sort(
+    input: &str,
+    output: &str,
+    opts: &CsvReadOpts,
+    by: &[SortKey],
+    dedup_by: &[String],
+    memory_limit: usize,
+    temp_dir: Option<&Path>,
+) -> Result<()> {
+    let (mut reader, headers) = build_reader(input, opts)?;
+    let keys = by
+        .iter()
+        .map(|key| Ok((resolve_column(&headers, &key.column)?, key.clone())))
+        .collect::<Result<Vec<_>>>()?;
+    let dedup = dedup_by
+        .iter()
+        .map(|spec| resolve_column(&headers, spec))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(opts.delimiter)
+        .flexible(opts.flexible)
+        .from_writer(get_writer(output)?);
+    if opts.header || opts.columns.is_some() {
+        writer.write_record(&headers)?;
+    }
+    let mut sink = Sink {
+        writer,
+        dedup,
+        seen: HashSet::new(),
+    };
+
+    let mut errors = RowErrors::new(opts)?;
+    let mut sorter = ExternalSorter::new(keys, memory_limit, temp_dir);
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        if sorter.keys.is_empty() {
+            // 只去重时无需排序，直接按输入顺序输出
+            sink.write(&record)?;
+        } else {
+            sorter.push(record)?;
+        }
+    }
+    errors.finish()?;
+    sorter.finish(|record| sink.write(&record))?;
+    sink.writer.flush()?;
+    Ok(())
+}
+
+///
+/// 排序列的取值，按 空值 < 数值 < 日期 < 文本 的顺序比较，保证任意混合的列也是全序
+///
+#[derive(Debug, Clone, PartialEq)]
+enum SortValue {
+    Null,
+    Number(f64),
+    /// 换算到 UTC 的时间，不同时区的值也能正确比较先后
+    Date(DateTime<Utc>),
+    Text(String),
+}
+
+impl SortValue {
+    fn parse(field: &str, kind: SortKind) -> Self {
+        let trimmed = field.trim();
+        if trimmed.is_empty() {
+            return SortValue::Null;
+        }
+        let number = || {
+            trimmed
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(SortValue::Number)
+        };
+        let date = || parse_timestamp(trimmed).map(SortValue::Date);
+        let value = match kind {
+            SortKind::Auto => number().or_else(date),
+            SortKind::Numeric => number(),
+            SortKind::Date => date(),
+            SortKind::Lexical => None,
+        };
+        value.unwrap_or_else(|| SortValue::Text(field.to_string()))
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortValue::Null => 0,
+            SortValue::Number(_) => 1,
+            SortValue::Date(_) => 2,
+            SortValue::Text(_) => 3,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            SortValue::Text(s) => s.len(),
+            _ => 0,
+        }
+    }
+}
+
+impl Eq for SortValue {}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Date(a), SortValue::Date(b)) => a.cmp(b),
+            (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+/// 带排序方向的取值，一行的排序键为 Vec<SortCell>，按字典序比较
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SortCell {
+    value: SortValue,
+    desc: bool,
+}
+
+impl PartialOrd for SortCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let ord = self.value.cmp(&other.value);
+        if self.desc {
+            ord.reverse()
+        } else {
+            ord
+        }
+    }
+}
+
+/// 已排序数据块的输出：按列去重后写入csv
+struct Sink<W: Write> {
+    writer: Writer<W>,
+    dedup: Vec<usize>,
+    seen: HashSet<[u8; 32]>,
+}
+
+impl<W: Write> Sink<W> {
+    fn write(&mut self, record: &StringRecord) -> Result<()> {
+        if !self.dedup.is_empty() {
+            let mut hasher = blake3::Hasher::new();
+            for &i in &self.dedup {
+                let field = record.get(i).unwrap_or_default();
+                // 写入长度避免 ("ab", "c") 和 ("a", "bc") 冲突
+                hasher.update(&(field.len() as u64).to_le_bytes());
+                hasher.update(field.as_bytes());
+            }
+            if !self.seen.insert(*hasher.finalize().as_bytes()) {
+                return Ok(());
+            }
+        }
+        self.writer.write_record(record)?;
+        Ok(())
+    }
+}
+
+///
+/// 外部排序：内存中的数据超出上限时排序后写入临时文件（一个 run），最后多路归并
+///
+struct ExternalSorter<'a> {
+    keys: Vec<(usize, SortKey)>,
+    memory_limit: usize,
+    temp_base: Option<&'a Path>,
+    temp_dir: Option<TempDir>,
+    chunk: Vec<(Vec<SortCell>, StringRecord)>,
+    chunk_size: usize,
+    runs: Vec<PathBuf>,
+    run_count: usize,
+}
+
+impl<'a> ExternalSorter<'a> {
+    fn new(keys: Vec<(usize, SortKey)>, memory_limit: usize, temp_base: Option<&'a Path>) -> Self {
+        Self {
+            keys,
+            memory_limit,
+            temp_base,
+            temp_dir: None,
+            chunk: Vec::new(),
+            chunk_size: 0,
+            runs: Vec::new(),
+            run_count: 0,
+        }
+    }
+
+    fn sort_key(&self, record: &StringRecord) -> Vec<SortCell> {
+        self.keys
+            .iter()
+            .map(|(index, key)| SortCell {
+                value: SortValue::parse(record.get(*index).unwrap_or_default(), key.kind),
+                desc: key.desc,
+            })
+            .collect()
+    }
+
+    fn push(&mut self, record: StringRecord) -> Result<()> {
+        let key = self.sort_key(&record);
+        // 粗略估计一行占用的内存：字段内容 + 字段边界 + 排序键
+        self.chunk_size += record.as_slice().len()
+            + record.len() * size_of::<usize>()
+            + key
+                .iter()
+                .map(|c| c.value.size() + size_of::<SortCell>())
+                .sum::<usize>()
+            + size_of::<(Vec<SortCell>, StringRecord)>();
+        self.chunk.push((key, record));
+        if self.chunk_size >= self.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// 将当前数据块排序后写入新的临时文件
+    fn spill(&mut self) -> Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+        let path = self.next_run_path()?;
+        let mut writer = WriterBuilder::new().flexible(true).from_path(&path)?;
+        self.chunk.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, record) in self.chunk.drain(..) {
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+        self.chunk_size = 0;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    fn next_run_path(&mut self) -> Result<PathBuf> {
+        if self.temp_dir.is_none() {
+            self.temp_dir = Some(TempDir::new(self.temp_base, "sort")?);
+        }
+        let dir = self.temp_dir.as_ref().expect("temp dir is created");
+        self.run_count += 1;
+        Ok(dir.file(&format!("run-{}.csv", self.run_count)))
+    }
+
+    /// 按顺序输出全部记录；未写出过临时文件时直接在内存中排序
+    fn finish(mut self, mut emit: impl FnMut(StringRecord) -> Result<()>) -> Result<()> {
+        if self.runs.is_empty() {
+            self.chunk.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, record) in self.chunk.drain(..) {
+                emit(record)?;
+            }
+            return Ok(());
+        }
+        self.spill()?;
+        // run 数过多时先分组归并成较少的 run，组内按 run 顺序归并以保持稳定
+        while self.runs.len() > MAX_MERGE_FANIN {
+            let runs = std::mem::take(&mut self.runs);
+            for group in runs.chunks(MAX_MERGE_FANIN) {
+                let path = self.next_run_path()?;
+                let mut writer = WriterBuilder::new().flexible(true).from_path(&path)?;
+                self.merge(group, |record| Ok(writer.write_record(&record)?))?;
+                writer.flush()?;
+                self.runs.push(path);
+            }
+        }
+        let runs = std::mem::take(&mut self.runs);
+        self.merge(&runs, emit)
+    }
+
+    /// 多路归并若干个已排序的 run，键相同时先输出序号小（更早写出）的 run 中的行
+    fn merge(
+        &self,
+        runs: &[PathBuf],
+        mut emit: impl FnMut(StringRecord) -> Result<()>,
+    ) -> Result<()> {
+        let mut readers = runs
+            .iter()
+            .map(|path| {
+                ReaderBuilder::new()
+                    .has_headers(false)
+                    .flexible(true)
+                    .from_path(path)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut heap = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            let mut record = StringRecord::new();
+            if reader.read_record(&mut record)? {
+                heap.push(Reverse(Head {
+                    key: self.sort_key(&record),
+                    run,
+                    record,
+                }));
+            }
+        }
+        while let Some(Reverse(head)) = heap.pop() {
+            let mut record = StringRecord::new();
+            if readers[head.run].read_record(&mut record)? {
+                heap.push(Reverse(Head {
+                    key: self.sort_key(&record),
+                    run: head.run,
+                    record,
+                }));
+            }
+            emit(head.record)?;
+        }
+        for path in runs {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// 归并时每个 run 当前的第一行
+struct Head {
+    key: Vec<SortCell>,
+    run: usize,
+    record: StringRecord,
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key).then(self.run.cmp(&other.run))
+    }
+}
+
+/// 本次操作使用的临时目录，结束（包括出错）时删除
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// 在 base（默认为系统临时目录）下创建 rcli-<name>-<pid>-<nanos> 目录
+    pub(crate) fn new(base: Option<&Path>, name: &str) -> Result<Self> {
+        let base = base
+            .map(Path::to_path_buf)
+            .unwrap_or_else(std::env::temp_dir);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.subsec_nanos();
+        let path = base.join(format!("rcli-{}-{}-{}", name, std::process::id(), nanos));
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    /// 目录下的文件路径
+    pub(crate) fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort_to_string(by: &[SortKey], dedup_by: &[String], memory_limit: usize) -> Result<String> {
+        let output = std::env::temp_dir().join(format!("rcli_test_sort_{}.csv", memory_limit));
+        let output = output.to_str().unwrap().to_string();
+        process_csv_sort(
+            "zcyj.csv",
+            &output,
+            &CsvReadOpts::default(),
+            by,
+            dedup_by,
+            memory_limit,
+            None,
+        )?;
+        let s = fs::read_to_string(&output)?;
+        fs::remove_file(output)?;
+        Ok(s)
+    }
+
+    #[test]
+    fn test_sort_value_order() {
+        let mut values = ["b", "", "10", "9", "2024-01-02", "a", "2024-01-01 08:00"]
+            .iter()
+            .map(|s| SortValue::parse(s, SortKind::Auto))
+            .collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(
+            values,
+            vec![
+                SortValue::Null,
+                SortValue::Number(9.0),
+                SortValue::Number(10.0),
+                SortValue::parse("2024-01-01T08:00:00Z", SortKind::Date),
+                SortValue::parse("2024-01-02", SortKind::Date),
+                SortValue::Text("a".to_string()),
+                SortValue::Text("b".to_string()),
+            ]
+        );
+        assert!(
+            SortValue::parse("10", SortKind::Lexical) < SortValue::parse("9", SortKind::Lexical)
+        );
+        // 带时区的值按换算到 UTC 后的时间比较
+        assert!(
+            SortValue::parse("2024-01-01T10:00:00+08:00", SortKind::Date)
+                < SortValue::parse("2024-01-01T05:00:00Z", SortKind::Date)
+        );
+    }
+
+    #[test]
+    fn test_external_sort_matches_in_memory() -> Result<()> {
+        let by = vec![
+            SortKey {
+                column: "Read Count".to_string(),
+                desc: true,
+                kind: SortKind::Numeric,
+            },
+            SortKey {
+                column: "Publish Time".to_string(),
+                desc: false,
+                kind: SortKind::Date,
+            },
+        ];
+        let in_memory = sort_to_string(&by, &[], 1 << 30)?;
+        // 每行单独写出一个 run，触发多轮归并
+        let external = sort_to_string(&by, &[], 1)?;
+        assert_eq!(in_memory, external);
+
+        let mut reader = csv::Reader::from_reader(in_memory.as_bytes());
+        let counts = reader
+            .records()
+            .map(|r| Ok(r?[3].parse::<u64>()?))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(counts.len(), 339);
+        assert!(counts.windows(2).all(|w| w[0] >= w[1]));
+
+        let dedup = sort_to_string(&by, &["Position".to_string()], 1)?;
+        assert!(dedup.starts_with(&in_memory.lines().take(2).collect::<Vec<_>>().join("\n")));
+        assert!(dedup.lines().count() < 10);
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use super::{
+    csv_convert::{build_reader, resolve_column},
+    csv_errors::RowErrors,
+};
+use crate::cli::CsvSplitOpts;
+use anyhow::Result;
+use csv::{Writer, WriterBuilder};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+/// --by 拆分时同时打开的文件数上限，取值过多通常意味着选错了列
+const MAX_PARTS: usize = 1024;
+
+///
+/// 将csv拆分为多个文件，每个文件都带表头，返回按创建顺序排列的输出文件
+///
+/// - --rows N：每 N 行一个文件，命名为 <prefix>_0001.csv, <prefix>_0002.csv, ...
+/// - --by 列：每个取值一个文件，命名为 <prefix>_<取值>.csv，取值中不能用于文件名的字符替换为 '_'
+///
+pub fn process_csv_split(opts: &CsvSplitOpts) -> Result<Vec<PathBuf>> {
+    let (mut reader, headers) = build_reader(&opts.input, &opts.read)?;
+    let by = opts
+        .by
+        .as_deref()
+        .map(|spec| resolve_column(&headers, spec))
+        .transpose()?;
+    let prefix = match &opts.prefix {
+        Some(prefix) => prefix.clone(),
+        None if opts.input == "-" => "part".to_string(),
+        None => Path::new(&opts.input)
+            .file_stem()
+            .map_or("part".to_string(), |s| s.to_string_lossy().into_owned()),
+    };
+    let mut parts = Parts {
+        dir: &opts.out_dir,
+        prefix,
+        delimiter: opts.read.delimiter,
+        flexible: opts.read.flexible,
+        headers: &headers,
+        paths: Vec::new(),
+        names: HashSet::new(),
+    };
+
+    // --by 时每个取值一个 writer，取值 -> writers 中的下标；--rows 时只保留当前的 writer
+    let mut writers: Vec<Writer<File>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut rows = 0;
+    let mut errors = RowErrors::new(&opts.read)?;
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        let part = match (by, opts.rows) {
+            (Some(by), _) => {
+                let value = record.get(by).unwrap_or_default();
+                match index.get(value) {
+                    Some(&part) => part,
+                    None => {
+                        if writers.len() >= MAX_PARTS {
+                            anyhow::bail!(
+                                "column {:?} has more than {} distinct values",
+                                &headers[by],
+                                MAX_PARTS
+                            );
+                        }
+                        writers.push(parts.open(&sanitize(value))?);
+                        index.insert(value.to_string(), writers.len() - 1);
+                        writers.len() - 1
+                    }
+                }
+            }
+            (None, Some(limit)) => {
+                if rows % limit == 0 {
+                    // 上一个文件已写满，写出后关闭再打开下一个
+                    if let Some(mut writer) = writers.pop() {
+                        writer.flush()?;
+                    }
+                    writers.push(parts.open(&format!("{:04}", parts.paths.len() + 1))?);
+                }
+                rows += 1;
+                0
+            }
+            (None, None) => unreachable!("clap requires --rows or --by"),
+        };
+        writers[part].write_record(&record)?;
+    }
+    errors.finish()?;
+
+    for writer in &mut writers {
+        writer.flush()?;
+    }
+    Ok(parts.paths)
+}
+
+struct Parts<'a> {
+    dir: &'a Path,
+    prefix: String,
+    delimiter: u8,
+    flexible: bool,
+    headers: &'a csv::StringRecord,
+    paths: Vec<PathBuf>,
+    names: HashSet<String>,
+}
+
+impl Parts<'_> {
+    /// 创建一个新的输出文件并写入表头；文件名冲突时追加 _2, _3, ...
+    fn open(&mut self, suffix: &str) -> Result<Writer<File>> {
+        let mut name = format!("{}_{}", self.prefix, suffix);
+        let mut n = 1;
+        while !self.names.insert(name.clone()) {
+            n += 1;
+            name = format!("{}_{}_{}", self.prefix, suffix, n);
+        }
+        let path = self.dir.join(format!("{}.csv", name));
+        let mut writer = WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .flexible(self.flexible)
+            .from_path(&path)?;
+        writer.write_record(self.headers)?;
+        self.paths.push(path);
+        Ok(writer)
+    }
+}
+
+/// 将单元格的值转换为可用作文件名的字符串
+fn sanitize(value: &str) -> String {
+    if value.is_empty() {
+        return "empty".to_string();
+    }
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::CsvReadOpts, process::csv_sort::TempDir};
+    use std::fs;
+
+    fn split(rows: Option<u64>, by: Option<&str>, dir: &TempDir) -> Result<Vec<PathBuf>> {
+        process_csv_split(&CsvSplitOpts {
+            input: "zcyj.csv".to_string(),
+            rows,
+            by: by.map(|s| s.to_string()),
+            out_dir: dir.file(""),
+            prefix: None,
+            read: CsvReadOpts::default(),
+        })
+    }
+
+    #[test]
+    fn test_process_csv_split() -> Result<()> {
+        let header = fs::read_to_string("zcyj.csv")?
+            .lines()
+            .next()
+            .unwrap()
+            .to_string();
+
+        let dir = TempDir::new(None, "test-split")?;
+        let paths = split(Some(100), None, &dir)?;
+        let names: Vec<_> = paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "zcyj_0001.csv",
+                "zcyj_0002.csv",
+                "zcyj_0003.csv",
+                "zcyj_0004.csv"
+            ]
+        );
+        let mut total = 0;
+        for path in &paths {
+            let mut reader = csv::Reader::from_path(path)?;
+            assert_eq!(
+                reader.headers()?.iter().collect::<Vec<_>>().join(","),
+                header
+            );
+            total += reader.records().count();
+        }
+        assert_eq!(total, 339);
+
+        let dir = TempDir::new(None, "test-split-by")?;
+        let paths = split(None, Some("Position"), &dir)?;
+        assert!(paths[0].ends_with("zcyj_1.csv"));
+        for path in &paths {
+            let mut reader = csv::Reader::from_path(path)?;
+            let values: HashSet<String> = reader
+                .records()
+                .map(|r| Ok(r?[2].to_string()))
+                .collect::<Result<_>>()?;
+            assert_eq!(values.len(), 1);
+        }
+
+        assert_eq!(sanitize("a/b c"), "a_b_c");
+        assert_eq!(sanitize(""), "empty");
+        Ok(())
+    }
+}
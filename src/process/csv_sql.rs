@@ -0,0 +1,1075 @@
+use super::{
+    csv_filter::{as_number, compare, syntax_error, truthy, CmpOp},
+    csv_output::value_to_field,
+};
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{Number, Value};
+use std::{cmp::Ordering, collections::HashSet};
+
+/// 出错时提示的表达式来源
+const WHAT: &str = "query";
+
+/// 保留字，不能作为未加引号的别名
+const KEYWORDS: &[&str] = &[
+    "SELECT", "DISTINCT", "FROM", "AS", "JOIN", "INNER", "LEFT", "OUTER", "ON", "WHERE", "GROUP",
+    "BY", "HAVING", "ORDER", "ASC", "DESC", "LIMIT", "OFFSET", "AND", "OR", "NOT", "LIKE", "IS",
+    "IN", "NULL", "TRUE", "FALSE",
+];
+
+///
+/// SQL 查询的子集：
+///
+/// ```text
+/// SELECT [DISTINCT] * | expr [AS name], ...
+/// FROM table [AS alias]
+/// [[INNER | LEFT [OUTER]] JOIN table [AS alias] ON expr] ...
+/// [WHERE expr] [GROUP BY expr, ...] [HAVING expr]
+/// [ORDER BY expr [ASC | DESC], ...] [LIMIT n [OFFSET m]]
+/// ```
+///
+/// - 表名和列名可以使用双引号，如 `"Read Count"`，字符串使用单引号
+/// - 运算：+ - * / %，= != <> < <= > >=，AND OR NOT，LIKE，IS [NOT] NULL，[NOT] IN (...)
+/// - 聚合函数：COUNT(*) COUNT([DISTINCT] expr) SUM AVG MIN MAX
+/// - 普通函数：LOWER UPPER LENGTH ABS ROUND COALESCE
+///
+#[derive(Debug)]
+pub(crate) struct Query {
+    pub(crate) distinct: bool,
+    pub(crate) select: Vec<SelectItem>,
+    pub(crate) from: TableRef,
+    pub(crate) joins: Vec<Join>,
+    pub(crate) filter: Option<Expr>,
+    pub(crate) group_by: Vec<Expr>,
+    pub(crate) having: Option<Expr>,
+    pub(crate) order_by: Vec<(Expr, bool)>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) offset: usize,
+}
+
+#[derive(Debug)]
+pub(crate) enum SelectItem {
+    Wildcard,
+    Expr { expr: Expr, name: String },
+}
+
+#[derive(Debug)]
+pub(crate) struct TableRef {
+    pub(crate) name: String,
+    pub(crate) alias: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct Join {
+    pub(crate) left: bool,
+    pub(crate) table: TableRef,
+    pub(crate) on: Expr,
+}
+
+#[derive(Debug)]
+pub(crate) enum Expr {
+    Literal(Value),
+    /// 列引用，index 在 bind 之后指向行中的位置
+    Column {
+        table: Option<String>,
+        name: String,
+        index: usize,
+    },
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cmp(CmpOp, Box<Expr>, Box<Expr>),
+    Arith(ArithOp, Box<Expr>, Box<Expr>),
+    Like(Box<Expr>, Regex, bool),
+    IsNull(Box<Expr>, bool),
+    In(Box<Expr>, Vec<Expr>, bool),
+    Func(Func, Vec<Expr>),
+    /// 聚合函数，arg 为 None 表示 COUNT(*)
+    Agg {
+        func: AggFunc,
+        arg: Option<Box<Expr>>,
+        distinct: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Func {
+    Lower,
+    Upper,
+    Length,
+    Abs,
+    Round,
+    Coalesce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AggFunc {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Quoted(String),
+    Str(String),
+    Num(Value),
+    Sym(&'static str),
+    Eof,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    /// (token, 起始位置, 结束位置)
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl Query {
+    /// 解析查询语句，出错时指出出错的位置
+    pub(crate) fn parse(src: &str) -> Result<Self> {
+        let tokens = tokenize(src)?;
+        let mut parser = Parser {
+            src,
+            tokens,
+            pos: 0,
+        };
+        let query = parser.parse_query()?;
+        parser.eat_sym(";");
+        if parser.peek() != &Token::Eof {
+            return Err(parser.error("unexpected token"));
+        }
+        Ok(query)
+    }
+
+    /// 是否需要按组计算：有 GROUP BY 或在输出、HAVING、ORDER BY 中使用了聚合函数
+    pub(crate) fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty()
+            || self.having.is_some()
+            || self.select.iter().any(|item| match item {
+                SelectItem::Expr { expr, .. } => expr.has_aggregate(),
+                SelectItem::Wildcard => false,
+            })
+            || self.order_by.iter().any(|(expr, _)| expr.has_aggregate())
+    }
+}
+
+impl Expr {
+    ///
+    /// 将列引用解析为行中的位置，scope 为 (表别名, 列名) 列表，不带表名的列名必须唯一
+    ///
+    pub(crate) fn bind(&mut self, scope: &[(String, String)]) -> Result<()> {
+        match self {
+            Expr::Column { table, name, index } => {
+                let matches: Vec<usize> = scope
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (t, c))| {
+                        c == name && table.as_ref().is_none_or(|table| table == t)
+                    })
+                    .map(|(i, _)| i)
+                    .collect();
+                let full_name = match table {
+                    Some(table) => format!("{}.{}", table, name),
+                    None => name.clone(),
+                };
+                match matches[..] {
+                    [i] => *index = i,
+                    [] => anyhow::bail!("unknown column {:?}", full_name),
+                    _ => anyhow::bail!(
+                        "ambiguous column {:?}, qualify it with a table name",
+                        full_name
+                    ),
+                }
+            }
+            Expr::Literal(_) => {}
+            Expr::Neg(e) | Expr::Not(e) | Expr::Like(e, _, _) | Expr::IsNull(e, _) => {
+                e.bind(scope)?
+            }
+            Expr::And(l, r) | Expr::Or(l, r) | Expr::Cmp(_, l, r) | Expr::Arith(_, l, r) => {
+                l.bind(scope)?;
+                r.bind(scope)?;
+            }
+            Expr::In(e, list, _) => {
+                e.bind(scope)?;
+                for item in list {
+                    item.bind(scope)?;
+                }
+            }
+            Expr::Func(_, args) => {
+                for arg in args {
+                    arg.bind(scope)?;
+                }
+            }
+            Expr::Agg { arg, .. } => {
+                if let Some(arg) = arg {
+                    arg.bind(scope)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn has_aggregate(&self) -> bool {
+        match self {
+            Expr::Agg { .. } => true,
+            Expr::Literal(_) | Expr::Column { .. } => false,
+            Expr::Neg(e) | Expr::Not(e) | Expr::Like(e, _, _) | Expr::IsNull(e, _) => {
+                e.has_aggregate()
+            }
+            Expr::And(l, r) | Expr::Or(l, r) | Expr::Cmp(_, l, r) | Expr::Arith(_, l, r) => {
+                l.has_aggregate() || r.has_aggregate()
+            }
+            Expr::In(e, list, _) => e.has_aggregate() || list.iter().any(Expr::has_aggregate),
+            Expr::Func(_, args) => args.iter().any(Expr::has_aggregate),
+        }
+    }
+
+    ///
+    /// 计算表达式的值，rows 为一组行：普通表达式使用第一行，聚合函数使用全部行
+    ///
+    pub(crate) fn eval(&self, rows: &[&[Value]]) -> Value {
+        match self {
+            Expr::Literal(v) => v.clone(),
+            Expr::Column { index, .. } => {
+                rows.first().map_or(Value::Null, |row| row[*index].clone())
+            }
+            Expr::Neg(e) => match e.eval(rows) {
+                Value::Number(n) if n.is_i64() => {
+                    let n = n.as_i64().unwrap_or_default();
+                    n.checked_neg()
+                        .map_or_else(|| float_value(-(n as f64)), Value::from)
+                }
+                v => as_number(&v).map_or(Value::Null, |n| float_value(-n)),
+            },
+            Expr::Not(e) => Value::Bool(!truthy(&e.eval(rows))),
+            Expr::And(l, r) => Value::Bool(truthy(&l.eval(rows)) && truthy(&r.eval(rows))),
+            Expr::Or(l, r) => Value::Bool(truthy(&l.eval(rows)) || truthy(&r.eval(rows))),
+            Expr::Cmp(op, l, r) => Value::Bool(compare(*op, &l.eval(rows), &r.eval(rows))),
+            Expr::Arith(op, l, r) => arith(*op, &l.eval(rows), &r.eval(rows)),
+            Expr::Like(e, re, negate) => {
+                let v = e.eval(rows);
+                Value::Bool(!v.is_null() && re.is_match(&value_to_field(&v)) != *negate)
+            }
+            Expr::IsNull(e, negate) => {
+                let v = e.eval(rows);
+                Value::Bool((v.is_null() || v.as_str() == Some("")) != *negate)
+            }
+            Expr::In(e, list, negate) => {
+                let v = e.eval(rows);
+                let found = list
+                    .iter()
+                    .any(|item| compare(CmpOp::Eq, &v, &item.eval(rows)));
+                Value::Bool(found != *negate)
+            }
+            Expr::Func(func, args) => {
+                let args: Vec<Value> = args.iter().map(|a| a.eval(rows)).collect();
+                call(*func, &args)
+            }
+            Expr::Agg {
+                func,
+                arg,
+                distinct,
+            } => {
+                let Some(arg) = arg else {
+                    return Value::from(rows.len());
+                };
+                let mut values: Vec<Value> = rows
+                    .iter()
+                    .map(|row| arg.eval(&[row]))
+                    .filter(|v| !v.is_null())
+                    .collect();
+                if *distinct {
+                    let mut seen = HashSet::new();
+                    values.retain(|v| seen.insert(v.to_string()));
+                }
+                aggregate(*func, values)
+            }
+        }
+    }
+}
+
+/// 对值排序时使用的全序：null < bool < 数字 < 字符串，能转换为数字的字符串按数字比较
+pub(crate) fn cmp_values(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        v if as_number(v).is_some() => 2,
+        _ => 3,
+    };
+    match (a, b) {
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        _ => match (as_number(a), as_number(b)) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            _ => rank(a)
+                .cmp(&rank(b))
+                .then_with(|| value_to_field(a).cmp(&value_to_field(b))),
+        },
+    }
+}
+
+/// ROUND 的位数参数的取值范围
+const MAX_ROUND_DIGITS: i64 = 308;
+
+fn float_value(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+/// 四则运算：两侧都是整数时结果为整数（除法除外），任意一侧不是数字时为 null
+fn arith(op: ArithOp, l: &Value, r: &Value) -> Value {
+    if let (Some(a), Some(b)) = (l.as_i64(), r.as_i64()) {
+        let result = match op {
+            ArithOp::Add => a.checked_add(b),
+            ArithOp::Sub => a.checked_sub(b),
+            ArithOp::Mul => a.checked_mul(b),
+            ArithOp::Mod => a.checked_rem(b),
+            ArithOp::Div => None,
+        };
+        if let Some(n) = result {
+            return Value::from(n);
+        }
+    }
+    let (Some(a), Some(b)) = (as_number(l), as_number(r)) else {
+        return Value::Null;
+    };
+    match op {
+        ArithOp::Add => float_value(a + b),
+        ArithOp::Sub => float_value(a - b),
+        ArithOp::Mul => float_value(a * b),
+        ArithOp::Div | ArithOp::Mod if b == 0.0 => Value::Null,
+        ArithOp::Div => float_value(a / b),
+        ArithOp::Mod => float_value(a % b),
+    }
+}
+
+fn call(func: Func, args: &[Value]) -> Value {
+    let arg = args.first().cloned().unwrap_or(Value::Null);
+    if arg.is_null() && func != Func::Coalesce {
+        return Value::Null;
+    }
+    match func {
+        Func::Lower => Value::String(value_to_field(&arg).to_lowercase()),
+        Func::Upper => Value::String(value_to_field(&arg).to_uppercase()),
+        Func::Length => Value::from(value_to_field(&arg).chars().count()),
+        Func::Abs => match arg.as_i64() {
+            Some(n) => Value::from(n.saturating_abs()),
+            None => as_number(&arg).map_or(Value::Null, |n| float_value(n.abs())),
+        },
+        Func::Round => {
+            // f64 的十进制指数范围约为 ±308，超出范围的位数没有意义
+            let digits = args
+                .get(1)
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0)
+                .clamp(-MAX_ROUND_DIGITS, MAX_ROUND_DIGITS) as i32;
+            match as_number(&arg) {
+                Some(_) if digits == 0 && arg.is_i64() => arg,
+                // 负数位数舍入到十位、百位等，用除法避免 0.01 这类缩放系数的误差
+                Some(n) if digits <= 0 => {
+                    let scale = 10f64.powi(-digits);
+                    let rounded = (n / scale).round() * scale;
+                    // 超出 i64 范围时保留浮点数，避免 as 转换饱和
+                    if rounded.abs() < i64::MAX as f64 {
+                        Value::from(rounded as i64)
+                    } else {
+                        float_value(rounded)
+                    }
+                }
+                Some(n) => {
+                    let scale = 10f64.powi(digits);
+                    let scaled = n * scale;
+                    // 位数超出 f64 的精度时舍入不改变值
+                    if scaled.is_finite() {
+                        float_value(scaled.round() / scale)
+                    } else {
+                        float_value(n)
+                    }
+                }
+                None => Value::Null,
+            }
+        }
+        Func::Coalesce => args
+            .iter()
+            .find(|v| !v.is_null())
+            .cloned()
+            .unwrap_or(Value::Null),
+    }
+}
+
+/// 计算聚合函数，values 已去掉 null
+fn aggregate(func: AggFunc, values: Vec<Value>) -> Value {
+    match func {
+        AggFunc::Count => Value::from(values.len()),
+        AggFunc::Sum | AggFunc::Avg => {
+            let numbers: Vec<f64> = values.iter().filter_map(as_number).collect();
+            if numbers.is_empty() {
+                return Value::Null;
+            }
+            let sum: f64 = numbers.iter().sum();
+            if func == AggFunc::Avg {
+                return float_value(sum / numbers.len() as f64);
+            }
+            let ints: Option<Vec<i64>> = values.iter().map(|v| v.as_i64()).collect();
+            match ints.and_then(|ints| ints.into_iter().try_fold(0i64, |acc, n| acc.checked_add(n)))
+            {
+                Some(sum) => Value::from(sum),
+                None => float_value(sum),
+            }
+        }
+        AggFunc::Min => values.into_iter().min_by(cmp_values).unwrap_or(Value::Null),
+        AggFunc::Max => values.into_iter().max_by(cmp_values).unwrap_or(Value::Null),
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].0.clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn error(&self, msg: &str) -> anyhow::Error {
+        syntax_error(WHAT, self.src, self.tokens[self.pos].1, msg)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(word) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.is_keyword(keyword) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if !self.eat_keyword(keyword) {
+            return Err(self.error(&format!("expected {}", keyword)));
+        }
+        Ok(())
+    }
+
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        if matches!(self.peek(), Token::Sym(s) if *s == sym) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> Result<()> {
+        if !self.eat_sym(sym) {
+            return Err(self.error(&format!("expected `{}`", sym)));
+        }
+        Ok(())
+    }
+
+    /// 表名、列名或别名：未加引号时不能是保留字
+    fn parse_name(&mut self) -> Result<String> {
+        match self.peek().clone() {
+            Token::Quoted(name) => {
+                self.next();
+                Ok(name)
+            }
+            Token::Ident(name) if !is_reserved(&name) => {
+                self.next();
+                Ok(name)
+            }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    /// 省略 AS 的别名：加引号的名字或非保留字
+    fn at_alias(&self) -> bool {
+        match self.peek() {
+            Token::Quoted(_) => true,
+            Token::Ident(word) => !is_reserved(word),
+            _ => false,
+        }
+    }
+
+    fn parse_usize(&mut self) -> Result<usize> {
+        match self.peek().clone() {
+            Token::Num(Value::Number(n)) if n.is_u64() => {
+                self.next();
+                Ok(n.as_u64().unwrap_or_default() as usize)
+            }
+            _ => Err(self.error("expected a non-negative integer")),
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.eat_keyword("DISTINCT");
+        let mut select = Vec::new();
+        loop {
+            select.push(self.parse_select_item()?);
+            if !self.eat_sym(",") {
+                break;
+            }
+        }
+
+        self.expect_keyword("FROM")?;
+        let from = self.parse_table()?;
+        let mut joins = Vec::new();
+        loop {
+            let left = if self.eat_keyword("LEFT") {
+                self.eat_keyword("OUTER");
+                true
+            } else {
+                self.eat_keyword("INNER");
+                false
+            };
+            if !self.eat_keyword("JOIN") {
+                if left {
+                    return Err(self.error("expected JOIN"));
+                }
+                break;
+            }
+            let table = self.parse_table()?;
+            self.expect_keyword("ON")?;
+            let on = self.parse_expr()?;
+            joins.push(Join { left, table, on });
+        }
+
+        let filter = if self.eat_keyword("WHERE") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        let mut group_by = Vec::new();
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            loop {
+                group_by.push(self.parse_expr()?);
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+        }
+        let having = if self.eat_keyword("HAVING") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let expr = self.parse_expr()?;
+                let desc = if self.eat_keyword("DESC") {
+                    true
+                } else {
+                    self.eat_keyword("ASC");
+                    false
+                };
+                order_by.push((expr, desc));
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+        }
+        let mut limit = None;
+        let mut offset = 0;
+        if self.eat_keyword("LIMIT") {
+            limit = Some(self.parse_usize()?);
+            if self.eat_keyword("OFFSET") {
+                offset = self.parse_usize()?;
+            }
+        }
+        Ok(Query {
+            distinct,
+            select,
+            from,
+            joins,
+            filter,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem> {
+        if self.eat_sym("*") {
+            return Ok(SelectItem::Wildcard);
+        }
+        let start = self.tokens[self.pos].1;
+        let expr = self.parse_expr()?;
+        let end = self.tokens[self.pos - 1].2;
+        let name = if self.eat_keyword("AS") || self.at_alias() {
+            self.parse_name()?
+        } else if let Expr::Column { name, .. } = &expr {
+            name.clone()
+        } else {
+            self.src[start..end].to_string()
+        };
+        Ok(SelectItem::Expr { expr, name })
+    }
+
+    fn parse_table(&mut self) -> Result<TableRef> {
+        let name = self.parse_name()?;
+        let alias = if self.eat_keyword("AS") || self.at_alias() {
+            self.parse_name()?
+        } else {
+            name.clone()
+        };
+        Ok(TableRef { name, alias })
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while self.eat_keyword("OR") {
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_not()?;
+        while self.eat_keyword("AND") {
+            let right = self.parse_not()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_cmp()
+    }
+
+    fn parse_cmp(&mut self) -> Result<Expr> {
+        let left = self.parse_additive()?;
+        let op = match self.peek() {
+            Token::Sym("=") | Token::Sym("==") => Some(CmpOp::Eq),
+            Token::Sym("!=") | Token::Sym("<>") => Some(CmpOp::Ne),
+            Token::Sym("<") => Some(CmpOp::Lt),
+            Token::Sym("<=") => Some(CmpOp::Le),
+            Token::Sym(">") => Some(CmpOp::Gt),
+            Token::Sym(">=") => Some(CmpOp::Ge),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.next();
+            let right = self.parse_additive()?;
+            return Ok(Expr::Cmp(op, Box::new(left), Box::new(right)));
+        }
+        if self.eat_keyword("IS") {
+            let negate = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull(Box::new(left), negate));
+        }
+        let negate = self.eat_keyword("NOT");
+        if self.eat_keyword("LIKE") {
+            let Token::Str(pattern) = self.peek().clone() else {
+                return Err(self.error("expected a quoted LIKE pattern"));
+            };
+            self.next();
+            return Ok(Expr::Like(Box::new(left), like_regex(&pattern)?, negate));
+        }
+        if self.eat_keyword("IN") {
+            self.expect_sym("(")?;
+            let mut list = Vec::new();
+            loop {
+                list.push(self.parse_additive()?);
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+            self.expect_sym(")")?;
+            return Ok(Expr::In(Box::new(left), list, negate));
+        }
+        if negate {
+            return Err(self.error("expected LIKE or IN"));
+        }
+        Ok(left)
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr> {
+        let mut left = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Token::Sym("+") => ArithOp::Add,
+                Token::Sym("-") => ArithOp::Sub,
+                _ => break,
+            };
+            self.next();
+            let right = self.parse_multiplicative()?;
+            left = Expr::Arith(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Token::Sym("*") => ArithOp::Mul,
+                Token::Sym("/") => ArithOp::Div,
+                Token::Sym("%") => ArithOp::Mod,
+                _ => break,
+            };
+            self.next();
+            let right = self.parse_unary()?;
+            left = Expr::Arith(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.eat_sym("-") {
+            return Ok(Expr::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.peek().clone() {
+            Token::Num(n) => {
+                self.next();
+                Ok(Expr::Literal(n))
+            }
+            Token::Str(s) => {
+                self.next();
+                Ok(Expr::Literal(Value::String(s)))
+            }
+            Token::Sym("(") => {
+                self.next();
+                let expr = self.parse_expr()?;
+                self.expect_sym(")")?;
+                Ok(expr)
+            }
+            Token::Ident(word) if word.eq_ignore_ascii_case("NULL") => {
+                self.next();
+                Ok(Expr::Literal(Value::Null))
+            }
+            Token::Ident(word)
+                if word.eq_ignore_ascii_case("TRUE") || word.eq_ignore_ascii_case("FALSE") =>
+            {
+                self.next();
+                Ok(Expr::Literal(Value::Bool(
+                    word.eq_ignore_ascii_case("TRUE"),
+                )))
+            }
+            Token::Ident(word) if matches!(self.tokens[self.pos + 1].0, Token::Sym("(")) => {
+                self.parse_call(&word)
+            }
+            Token::Ident(_) | Token::Quoted(_) => {
+                let first = self.parse_name()?;
+                if self.eat_sym(".") {
+                    let name = self.parse_name()?;
+                    return Ok(Expr::Column {
+                        table: Some(first),
+                        name,
+                        index: usize::MAX,
+                    });
+                }
+                Ok(Expr::Column {
+                    table: None,
+                    name: first,
+                    index: usize::MAX,
+                })
+            }
+            Token::Eof => Err(self.error("unexpected end of query")),
+            _ => Err(self.error("expected a column, literal, function or `(`")),
+        }
+    }
+
+    fn parse_call(&mut self, name: &str) -> Result<Expr> {
+        let upper = name.to_ascii_uppercase();
+        let agg = match upper.as_str() {
+            "COUNT" => Some(AggFunc::Count),
+            "SUM" => Some(AggFunc::Sum),
+            "AVG" => Some(AggFunc::Avg),
+            "MIN" => Some(AggFunc::Min),
+            "MAX" => Some(AggFunc::Max),
+            _ => None,
+        };
+        let func = match upper.as_str() {
+            "LOWER" => Some((Func::Lower, 1, 1)),
+            "UPPER" => Some((Func::Upper, 1, 1)),
+            "LENGTH" => Some((Func::Length, 1, 1)),
+            "ABS" => Some((Func::Abs, 1, 1)),
+            "ROUND" => Some((Func::Round, 1, 2)),
+            "COALESCE" => Some((Func::Coalesce, 1, usize::MAX)),
+            _ => None,
+        };
+        if agg.is_none() && func.is_none() {
+            return Err(self.error(&format!("unknown function {}", name)));
+        }
+        self.next();
+        self.expect_sym("(")?;
+
+        if let Some(func) = agg {
+            if func == AggFunc::Count && self.eat_sym("*") {
+                self.expect_sym(")")?;
+                return Ok(Expr::Agg {
+                    func,
+                    arg: None,
+                    distinct: false,
+                });
+            }
+            let distinct = self.eat_keyword("DISTINCT");
+            let arg = self.parse_expr()?;
+            self.expect_sym(")")?;
+            return Ok(Expr::Agg {
+                func,
+                arg: Some(Box::new(arg)),
+                distinct,
+            });
+        }
+
+        let (func, min, max) = func.expect("function is known");
+        let mut args = Vec::new();
+        if !self.eat_sym(")") {
+            loop {
+                args.push(self.parse_expr()?);
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+            self.expect_sym(")")?;
+        }
+        if args.len() < min || args.len() > max {
+            return Err(self.error(&format!("wrong number of arguments for {}", upper)));
+        }
+        Ok(Expr::Func(func, args))
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+/// 将 LIKE 模式转换为正则：% 匹配任意字符串，_ 匹配单个字符
+fn like_regex(pattern: &str) -> Result<Regex> {
+    let mut re = String::from("^(?s)");
+    for c in pattern.chars() {
+        match c {
+            '%' => re.push_str(".*"),
+            '_' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Ok(Regex::new(&re)?)
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize, usize)>> {
+    const SYMBOLS: &[&str] = &[
+        "<=", ">=", "<>", "!=", "==", "=", "<", ">", ",", ".", "(", ")", "*", "+", "-", "/", "%",
+        ";",
+    ];
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '"' | '\'' | '`' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        // 连续两个引号表示引号本身
+                        Some((_, q)) if q == c => {
+                            if chars.peek().map(|&(_, n)| n) == Some(c) {
+                                chars.next();
+                                s.push(c);
+                            } else {
+                                break;
+                            }
+                        }
+                        Some((_, ch)) => s.push(ch),
+                        None => return Err(syntax_error(WHAT, src, start, "unterminated quote")),
+                    }
+                }
+                if c == '\'' {
+                    Token::Str(s)
+                } else {
+                    Token::Quoted(s)
+                }
+            }
+            c if c.is_ascii_digit() => {
+                let mut end = start;
+                while let Some(&(i, ch)) = chars.peek() {
+                    if ch.is_ascii_digit() || ch == '.' {
+                        end = i + 1;
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let text = &src[start..end];
+                let value = match text.parse::<i64>() {
+                    Ok(n) => Value::from(n),
+                    Err(_) => text
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(Number::from_f64)
+                        .map(Value::Number)
+                        .ok_or_else(|| syntax_error(WHAT, src, start, "invalid number"))?,
+                };
+                Token::Num(value)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, ch)) = chars.peek() {
+                    if ch.is_alphanumeric() || ch == '_' {
+                        end = i + ch.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                Token::Ident(src[start..end].to_string())
+            }
+            _ => {
+                let sym = SYMBOLS
+                    .iter()
+                    .find(|sym| src[start..].starts_with(**sym))
+                    .ok_or_else(|| {
+                        syntax_error(WHAT, src, start, &format!("unexpected character {:?}", c))
+                    })?;
+                for _ in 0..sym.len() {
+                    chars.next();
+                }
+                Token::Sym(sym)
+            }
+        };
+        let end = chars.peek().map_or(src.len(), |&(i, _)| i);
+        tokens.push((token, start, end));
+    }
+    tokens.push((Token::Eof, src.len(), src.len()));
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_query() -> Result<()> {
+        let query = Query::parse(
+            r#"SELECT Title, "Read Count" AS reads, COUNT(*) FROM zcyj z
+               LEFT JOIN metrics m ON z.Link = m.Link
+               WHERE Position = 1 AND Title LIKE '%领导%'
+               GROUP BY Title ORDER BY "Like Count" DESC, 2 LIMIT 10 OFFSET 5;"#,
+        )?;
+        let names: Vec<&str> = query
+            .select
+            .iter()
+            .map(|item| match item {
+                SelectItem::Expr { name, .. } => name.as_str(),
+                SelectItem::Wildcard => "*",
+            })
+            .collect();
+        assert_eq!(names, ["Title", "reads", "COUNT(*)"]);
+        assert_eq!(
+            (query.from.name.as_str(), query.from.alias.as_str()),
+            ("zcyj", "z")
+        );
+        assert!(query.joins[0].left);
+        assert_eq!(query.joins[0].table.alias, "m");
+        assert_eq!(query.order_by.len(), 2);
+        assert!(query.order_by[0].1);
+        assert_eq!((query.limit, query.offset), (Some(10), 5));
+        assert!(query.is_aggregate());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = Query::parse("SELECT a FROM").unwrap_err();
+        assert!(err.to_string().contains("at column 14"), "{}", err);
+        assert!(Query::parse("SELECT a FROM t WHERE").is_err());
+        assert!(Query::parse("SELECT foo(a) FROM t").is_err());
+        assert!(Query::parse("SELECT a FROM t LIMIT -1").is_err());
+        assert!(Query::parse("SELECT 'a FROM t").is_err());
+    }
+
+    #[test]
+    fn test_eval() -> Result<()> {
+        let scope: Vec<(String, String)> = [("t", "a"), ("t", "b"), ("u", "a")]
+            .iter()
+            .map(|(t, c)| (t.to_string(), c.to_string()))
+            .collect();
+        let rows = [
+            vec![json!(3), json!("Hello"), json!(null)],
+            vec![json!(4), json!("x"), json!(1)],
+        ];
+        let rows: Vec<&[Value]> = rows.iter().map(|r| r.as_slice()).collect();
+        let cases = [
+            ("t.a * 2 + 1", json!(7)),
+            ("t.a / 2", json!(1.5)),
+            ("LOWER(b) LIKE 'h_l%'", json!(true)),
+            ("u.a IS NULL AND b NOT IN ('x', 'y')", json!(true)),
+            ("COALESCE(u.a, -1)", json!(-1)),
+            ("ROUND(t.a / 2)", json!(2)),
+            ("ROUND(1234, -2)", json!(1200)),
+            ("ROUND(1250.5, -2)", json!(1300)),
+            ("ROUND(-1234, -1)", json!(-1230)),
+            ("ROUND(2.71828, 2)", json!(2.72)),
+            ("ROUND(1234, -4294967298)", json!(0)),
+            ("ROUND(1234, -400)", json!(0)),
+            ("ROUND(2.5, 4294967296)", json!(2.5)),
+            (
+                "ROUND(9223372036854775807 * 2.0, 0)",
+                json!(18446744073709551614.0),
+            ),
+            ("-(-9223372036854775807 - 1)", json!(9223372036854775808.0)),
+            ("SUM(t.a)", json!(7)),
+            ("AVG(t.a)", json!(3.5)),
+            ("COUNT(u.a)", json!(1)),
+            ("MAX(b)", json!("x")),
+        ];
+        for (src, expected) in cases {
+            let query = Query::parse(&format!("SELECT {} FROM t", src))?;
+            let SelectItem::Expr { mut expr, .. } = query.select.into_iter().next().unwrap() else {
+                unreachable!()
+            };
+            expr.bind(&scope)?;
+            assert_eq!(expr.eval(&rows), expected, "{}", src);
+        }
+        let mut expr = Query::parse("SELECT a FROM t")?.select.remove(0);
+        if let SelectItem::Expr { expr, .. } = &mut expr {
+            assert!(expr
+                .bind(&scope)
+                .unwrap_err()
+                .to_string()
+                .contains("ambiguous"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use super::{
+    csv_convert::build_reader,
+    csv_errors::RowErrors,
+    csv_show::{pad_to_width, truncate_to_width},
+    csv_types::infer_type,
+};
+use crate::cli::{ColumnType, CsvReadOpts};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use unicode_width::UnicodeWidthStr;
+
+/// 表格中 longest 列的最大显示宽度
+const MAX_LONGEST_WIDTH: usize = 40;
+
+///
+/// 单列的统计信息
+///
+#[derive(Debug, Serialize)]
+pub(crate) struct ColumnStats {
+    name: String,
+    /// 推断的列类型，全部为空时为 null
+    #[serde(rename = "type")]
+    ty: Option<ColumnType>,
+    count: usize,
+    nulls: usize,
+    distinct: usize,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: Option<f64>,
+    max_length: usize,
+    longest: String,
+    #[serde(skip)]
+    values: HashSet<String>,
+    #[serde(skip)]
+    sum: f64,
+    #[serde(skip)]
+    numbers: usize,
+}
+
+///
+/// 统计csv每一列的类型、空值数、不同值个数、数值范围/均值以及最长的值
+///
+/// json 为 true 时返回 JSON，否则返回对齐的文本表格
+///
+pub fn process_csv_stats(input: &str, opts: &CsvReadOpts, json: bool) -> Result<String> {
+    let (mut reader, headers) = build_reader(input, opts)?;
+    let mut stats: Vec<ColumnStats> = headers.iter().map(ColumnStats::new).collect();
+    let mut errors = RowErrors::new(opts)?;
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        for (i, column) in stats.iter_mut().enumerate() {
+            column.update(record.get(i).unwrap_or_default());
+        }
+    }
+    errors.finish()?;
+    for column in &mut stats {
+        column.finish();
+    }
+
+    if json {
+        Ok(serde_json::to_string_pretty(&stats)?)
+    } else {
+        Ok(render_table(&stats))
+    }
+}
+
+impl ColumnStats {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ty: None,
+            count: 0,
+            nulls: 0,
+            distinct: 0,
+            min: None,
+            max: None,
+            mean: None,
+            max_length: 0,
+            longest: String::new(),
+            values: HashSet::new(),
+            sum: 0.0,
+            numbers: 0,
+        }
+    }
+
+    fn update(&mut self, field: &str) {
+        self.count += 1;
+        let Some(ty) = infer_type(field) else {
+            self.nulls += 1;
+            return;
+        };
+        self.ty = Some(match self.ty {
+            None => ty,
+            Some(prev) => merge_type(prev, ty),
+        });
+        if matches!(ty, ColumnType::Int | ColumnType::Float) {
+            if let Ok(n) = field.parse::<f64>() {
+                self.min = Some(self.min.map_or(n, |m| m.min(n)));
+                self.max = Some(self.max.map_or(n, |m| m.max(n)));
+                self.sum += n;
+                self.numbers += 1;
+            }
+        }
+        let len = field.chars().count();
+        if len > self.max_length {
+            self.max_length = len;
+            self.longest = field.to_string();
+        }
+        if !self.values.contains(field) {
+            self.values.insert(field.to_string());
+        }
+    }
+
+    fn finish(&mut self) {
+        self.distinct = self.values.len();
+        self.values = HashSet::new();
+        if matches!(self.ty, Some(ColumnType::Int | ColumnType::Float)) {
+            if self.numbers > 0 {
+                self.mean = Some(self.sum / self.numbers as f64);
+            }
+        } else {
+            self.min = None;
+            self.max = None;
+        }
+    }
+}
+
+/// 合并两种类型：int 与 float 合并为 float，其余不同类型合并为 string
+fn merge_type(a: ColumnType, b: ColumnType) -> ColumnType {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (ColumnType::Int, ColumnType::Float) | (ColumnType::Float, ColumnType::Int) => {
+            ColumnType::Float
+        }
+        _ => ColumnType::String,
+    }
+}
+
+fn render_table(stats: &[ColumnStats]) -> String {
+    let fmt_num = |n: Option<f64>| {
+        n.map(|n| format!("{}", (n * 100.0).round() / 100.0))
+            .unwrap_or_default()
+    };
+    let header = [
+        "column", "type", "count", "nulls", "distinct", "min", "max", "mean", "max_len", "longest",
+    ];
+    let mut rows = vec![header.iter().map(|s| s.to_string()).collect::<Vec<_>>()];
+    for s in stats {
+        let longest = truncate_to_width(&s.longest, MAX_LONGEST_WIDTH);
+        rows.push(vec![
+            s.name.clone(),
+            s.ty.map(|t| t.to_string())
+                .unwrap_or_else(|| "null".to_string()),
+            s.count.to_string(),
+            s.nulls.to_string(),
+            s.distinct.to_string(),
+            fmt_num(s.min),
+            fmt_num(s.max),
+            fmt_num(s.mean),
+            s.max_length.to_string(),
+            longest,
+        ]);
+    }
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| rows.iter().map(|r| r[i].width()).max().unwrap_or(0))
+        .collect();
+    let mut out = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, w)| pad_to_width(cell, *w))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_stats() -> Result<()> {
+        let json = process_csv_stats("zcyj.csv", &CsvReadOpts::default(), true)?;
+        let stats: serde_json::Value = serde_json::from_str(&json)?;
+        let read = &stats[3];
+        assert_eq!(read["name"], "Read Count");
+        assert_eq!(read["type"], "int");
+        assert_eq!(read["nulls"], 0);
+        assert!(read["min"].as_f64().unwrap() <= read["mean"].as_f64().unwrap());
+        assert_eq!(stats[0]["type"], "datetime");
+        assert_eq!(stats[1]["type"], "string");
+        assert!(stats[1]["min"].is_null());
+
+        let table = process_csv_stats("assets/juventus.csv", &CsvReadOpts::default(), false)?;
+        assert!(table.starts_with("column"));
+        assert_eq!(table.lines().count(), 6);
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+use crate::cli::{ColumnType, DatetimeOutput, DatetimeZone};
+use anyhow::Result;
+use chrono::{
+    DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Utc,
+};
+use serde_json::{Number, Value};
+
+/// 不带时区的常见日期时间格式
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+];
+
+/// 常见日期格式
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d"];
+
+///
+/// 推断单元格的类型：空 -> null，true/false -> bool，整数/浮点数 -> number，其余保持字符串
+///
+pub(crate) fn infer_value(s: &str) -> Value {
+    if s.is_empty() {
+        return Value::Null;
+    }
+    if let Some(b) = parse_bool_literal(s) {
+        return Value::Bool(b);
+    }
+    // 超出 i64 的长整数（如证件号、卡号）保留为字符串，转为浮点数会丢失精度
+    if looks_like_int(s) {
+        return match s.parse::<i64>() {
+            Ok(n) => Value::Number(n.into()),
+            Err(_) => Value::String(s.to_string()),
+        };
+    }
+    if looks_like_float(s) {
+        if let Some(n) = s.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Value::Number(n);
+        }
+    }
+    Value::String(s.to_string())
+}
+
+///
+/// 推断单元格的列类型，空单元格返回 None
+///
+pub(crate) fn infer_type(s: &str) -> Option<ColumnType> {
+    match infer_value(s) {
+        Value::Null => None,
+        Value::Bool(_) => Some(ColumnType::Bool),
+        Value::Number(n) if n.is_i64() => Some(ColumnType::Int),
+        Value::Number(_) => Some(ColumnType::Float),
+        _ if parse_datetime(s).is_some() => Some(ColumnType::Datetime),
+        _ => Some(ColumnType::String),
+    }
+}
+
+///
+/// 按指定类型转换单元格，空单元格统一转换为 null，无法转换时返回错误
+///
+/// datetime 只校验并规范为不带时区的 ISO 8601；csv 转换输出时 RowBuilder 改用 DatetimeNormalizer
+///
+pub(crate) fn convert_value(s: &str, ty: ColumnType) -> Result<Value> {
+    if s.is_empty() {
+        return Ok(Value::Null);
+    }
+    let value = match ty {
+        ColumnType::String => Some(Value::String(s.to_string())),
+        ColumnType::Int => s
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .map(|n| Value::Number(n.into())),
+        ColumnType::Float => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number),
+        ColumnType::Bool => parse_bool(s.trim()).map(Value::Bool),
+        ColumnType::Datetime => parse_datetime(s.trim()).map(Value::String),
+    };
+    value.ok_or_else(|| anyhow::anyhow!("cannot parse {:?} as {}", s, ty))
+}
+
+///
+/// 解析常见格式的日期时间，返回 ISO 8601 格式的字符串
+///
+pub(crate) fn parse_datetime(s: &str) -> Option<String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.to_rfc3339());
+    }
+    for fmt in DATETIME_FORMATS {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(dt.format("%Y-%m-%dT%H:%M:%S").to_string());
+        }
+    }
+    for fmt in DATE_FORMATS {
+        if let Ok(d) = NaiveDate::parse_from_str(s, fmt) {
+            return Some(d.format("%Y-%m-%d").to_string());
+        }
+    }
+    None
+}
+
+///
+/// 解析常见格式的日期时间并换算为 UTC，不带时区的值按 UTC 处理；用于跨时区比较先后
+///
+pub(crate) fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    Some(match parse_common_datetime(s)? {
+        ParsedDatetime::Aware(dt) => dt.with_timezone(&Utc),
+        ParsedDatetime::Naive(dt) => dt.and_utc(),
+    })
+}
+
+/// 按常见格式解析出的日期时间，区分是否带时区
+enum ParsedDatetime {
+    Aware(DateTime<FixedOffset>),
+    Naive(NaiveDateTime),
+}
+
+/// 依次尝试 RFC 3339、DATETIME_FORMATS 和 DATE_FORMATS，只有日期时取当天 0 点
+fn parse_common_datetime(s: &str) -> Option<ParsedDatetime> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(ParsedDatetime::Aware(dt));
+    }
+    if let Some(dt) = DATETIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+    {
+        return Some(ParsedDatetime::Naive(dt));
+    }
+    let d = DATE_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(s, fmt).ok())?;
+    Some(ParsedDatetime::Naive(d.and_time(NaiveTime::MIN)))
+}
+
+///
+/// 按 --datetime 指定的格式解析日期时间列，并规范化为 RFC 3339 或 Unix 时间戳
+///
+/// 不带时区的值按 tz 所在时区解释；带时区的值转换到 tz 后输出。
+/// 格式匹配后剩余的内容以空白开头时被忽略，如 "Apr 18, 1990 (29)" 中的 " (29)"
+///
+pub(crate) struct DatetimeNormalizer {
+    format: Option<String>,
+    tz: DatetimeZone,
+    output: DatetimeOutput,
+}
+
+impl DatetimeNormalizer {
+    pub(crate) fn new(format: Option<String>, tz: DatetimeZone, output: DatetimeOutput) -> Self {
+        Self { format, tz, output }
+    }
+
+    /// 空单元格转换为 null，无法解析时返回错误
+    pub(crate) fn normalize(&self, s: &str) -> Result<Value> {
+        if s.is_empty() {
+            return Ok(Value::Null);
+        }
+        let dt = self.parse(s.trim()).ok_or_else(|| match &self.format {
+            Some(format) => {
+                anyhow::anyhow!("cannot parse {:?} as datetime with format {:?}", s, format)
+            }
+            None => anyhow::anyhow!("cannot parse {:?} as datetime", s),
+        })?;
+        Ok(match self.output {
+            DatetimeOutput::Rfc3339 => {
+                Value::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            DatetimeOutput::Epoch => Value::from(dt.timestamp()),
+        })
+    }
+
+    fn parse(&self, s: &str) -> Option<DateTime<FixedOffset>> {
+        let Some(format) = &self.format else {
+            return match parse_common_datetime(s)? {
+                ParsedDatetime::Aware(dt) => self.convert(dt),
+                ParsedDatetime::Naive(dt) => self.localize(dt),
+            };
+        };
+        // 依次尝试带时区、不带时区的日期时间以及只有日期的格式
+        let ignorable = |rest: &str| rest.is_empty() || rest.starts_with(char::is_whitespace);
+        if let Ok((dt, rest)) = DateTime::parse_and_remainder(s, format) {
+            if ignorable(rest) {
+                return self.convert(dt);
+            }
+        }
+        if let Ok((dt, rest)) = NaiveDateTime::parse_and_remainder(s, format) {
+            if ignorable(rest) {
+                return self.localize(dt);
+            }
+        }
+        match NaiveDate::parse_and_remainder(s, format) {
+            Ok((d, rest)) if ignorable(rest) => self.localize(d.and_time(NaiveTime::MIN)),
+            _ => None,
+        }
+    }
+
+    /// 将不带时区的值解释为 tz 中的本地时间，夏令时切换造成的歧义时间视为无法解析
+    fn localize(&self, dt: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        match self.tz {
+            DatetimeZone::Utc => Some(Utc.from_utc_datetime(&dt).fixed_offset()),
+            DatetimeZone::Local => Local
+                .from_local_datetime(&dt)
+                .single()
+                .map(|dt| dt.fixed_offset()),
+            DatetimeZone::Fixed(offset) => offset.from_local_datetime(&dt).single(),
+        }
+    }
+
+    fn convert(&self, dt: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        Some(match self.tz {
+            DatetimeZone::Utc => dt.with_timezone(&Utc).fixed_offset(),
+            DatetimeZone::Local => dt.with_timezone(&Local).fixed_offset(),
+            DatetimeZone::Fixed(offset) => dt.with_timezone(&offset),
+        })
+    }
+}
+
+fn parse_bool_literal(s: &str) -> Option<bool> {
+    if s.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if s.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    parse_bool_literal(s).or(match s.to_ascii_lowercase().as_str() {
+        "1" | "yes" | "y" => Some(true),
+        "0" | "no" | "n" => Some(false),
+        _ => None,
+    })
+}
+
+/// 整数：可选负号 + 数字，且不能有前导 0（避免把 "007" 这类编号转成数字）
+fn looks_like_int(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    !digits.is_empty()
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && (digits == "0" || !digits.starts_with('0'))
+}
+
+/// 浮点数：可选负号 + 数字、小数点和指数，符号只能出现在开头或指数中，排除 inf/NaN 等字面量
+fn looks_like_float(s: &str) -> bool {
+    let int_part = s.strip_prefix('-').unwrap_or(s);
+    let bytes = int_part.as_bytes();
+    bytes.iter().any(u8::is_ascii_digit)
+        && bytes.iter().enumerate().all(|(i, &b)| {
+            b.is_ascii_digit()
+                || matches!(b, b'.' | b'e' | b'E')
+                || (matches!(b, b'-' | b'+') && i > 0 && matches!(bytes[i - 1], b'e' | b'E'))
+        })
+        && !(int_part.starts_with('0') && int_part.len() > 1 && !int_part.starts_with("0."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_infer_value() {
+        assert_eq!(infer_value(""), Value::Null);
+        assert_eq!(infer_value("100001"), json!(100001));
+        assert_eq!(infer_value("-3"), json!(-3));
+        assert_eq!(infer_value("1.5"), json!(1.5));
+        assert_eq!(infer_value("TRUE"), json!(true));
+        assert_eq!(infer_value("007"), json!("007"));
+        // 超出 i64 的整数保持字符串，不丢失精度
+        assert_eq!(
+            infer_value("62220212345678901234"),
+            json!("62220212345678901234")
+        );
+        assert_eq!(
+            infer_value("-9223372036854775809"),
+            json!("-9223372036854775809")
+        );
+        // 开头的 + 不视为数字，指数中的符号仍然有效
+        assert_eq!(infer_value("+5"), json!("+5"));
+        assert_eq!(infer_value("+1.5"), json!("+1.5"));
+        assert_eq!(infer_value("1.5e+3"), json!(1500.0));
+        assert_eq!(infer_value("2e-2"), json!(0.02));
+        assert_eq!(infer_value("NaN"), json!("NaN"));
+        assert_eq!(
+            infer_value("2024-06-19 21:26:00"),
+            json!("2024-06-19 21:26:00")
+        );
+    }
+
+    #[test]
+    fn test_infer_type() {
+        assert_eq!(infer_type(""), None);
+        assert_eq!(infer_type("1"), Some(ColumnType::Int));
+        assert_eq!(infer_type("1.5"), Some(ColumnType::Float));
+        assert_eq!(infer_type("12345678901234567890"), Some(ColumnType::String));
+        assert_eq!(infer_type("false"), Some(ColumnType::Bool));
+        assert_eq!(
+            infer_type("2024-06-19 21:26:00"),
+            Some(ColumnType::Datetime)
+        );
+        assert_eq!(infer_type("Apr 18, 1990 (29)"), Some(ColumnType::String));
+    }
+
+    #[test]
+    fn test_convert_value() -> Result<()> {
+        assert_eq!(convert_value("42", ColumnType::Int)?, json!(42));
+        assert_eq!(convert_value("42", ColumnType::String)?, json!("42"));
+        assert_eq!(convert_value("yes", ColumnType::Bool)?, json!(true));
+        assert_eq!(convert_value("", ColumnType::Int)?, Value::Null);
+        assert_eq!(
+            convert_value("2024-06-19 21:26:00", ColumnType::Datetime)?,
+            json!("2024-06-19T21:26:00")
+        );
+        assert!(convert_value("abc", ColumnType::Int).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_datetime_normalizer() -> Result<()> {
+        let dob = DatetimeNormalizer::new(
+            Some("%b %d, %Y".into()),
+            DatetimeZone::Utc,
+            DatetimeOutput::Rfc3339,
+        );
+        assert_eq!(
+            dob.normalize("Apr 18, 1990 (29)")?,
+            json!("1990-04-18T00:00:00Z")
+        );
+        assert_eq!(dob.normalize("")?, Value::Null);
+        assert!(dob.normalize("18/04/1990").is_err());
+        assert!(dob.normalize("Apr 18, 1990x").is_err());
+
+        let tz = DatetimeZone::Fixed(FixedOffset::east_opt(8 * 3600).unwrap());
+        let auto = DatetimeNormalizer::new(None, tz, DatetimeOutput::Rfc3339);
+        assert_eq!(
+            auto.normalize("2024-06-19 21:26:00")?,
+            json!("2024-06-19T21:26:00+08:00")
+        );
+        assert_eq!(
+            auto.normalize("2024-06-19T13:26:00Z")?,
+            json!("2024-06-19T21:26:00+08:00")
+        );
+
+        let epoch =
+            DatetimeNormalizer::new(Some("%Y-%m-%d %H:%M:%S".into()), tz, DatetimeOutput::Epoch);
+        assert_eq!(epoch.normalize("2024-06-19 21:26:00")?, json!(1718803560));
+        Ok(())
+    }
+}
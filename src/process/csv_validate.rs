@@ -0,0 +1,470 @@
+use super::{
+    csv_convert::build_reader, csv_errors::RowErrors, csv_output::value_to_field,
+    csv_types::convert_value,
+};
+use crate::cli::{ColumnType, CsvReadOpts};
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{collections::HashMap, fs, io::Write};
+
+///
+/// 按规则文件校验csv，逐条输出违反规则的行号、列名和原因，返回违规总数
+///
+/// 规则文件可以是 YAML 格式的列规则：
+///
+/// ```yaml
+/// strict: true          # 不允许规则之外的列
+/// columns:
+///   id: { type: int, unique: true, nullable: false }
+///   email: { pattern: '^[^@]+@[^@]+$' }
+///   status: { enum: [active, inactive] }
+///   age: { type: int, min: 0, max: 150, required: false }
+/// ```
+///
+/// 也可以是 JSON Schema 的子集：properties 中的 type/format/pattern/enum/minimum/maximum，
+/// required 中的列必须存在且不能为空，additionalProperties: false 对应 strict。
+/// 其余关键字（如 minLength、format: email）会报错，而不是被静默忽略
+///
+pub fn process_csv_validate(
+    input: &str,
+    schema: &str,
+    opts: &CsvReadOpts,
+    writer: &mut dyn Write,
+) -> Result<usize> {
+    let schema = Schema::load(schema)?;
+    let (mut reader, headers) = build_reader(input, opts)?;
+    let mut report = Report { writer, count: 0 };
+
+    let mut checks = Vec::new();
+    for (name, rule) in schema.columns.iter() {
+        match headers.iter().position(|h| h == name) {
+            Some(index) => checks.push(Check::new(index, name, rule)?),
+            None if rule.required => report.violation(None, name, "required column is missing")?,
+            None => {}
+        }
+    }
+    if schema.strict {
+        for h in headers
+            .iter()
+            .filter(|h| !schema.columns.iter().any(|(name, _)| name == h))
+        {
+            report.violation(None, h, "unexpected column")?;
+        }
+    }
+
+    let mut rows = 0;
+    let mut invalid_rows = 0;
+    let mut errors = RowErrors::new(opts)?;
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.handle(e.position().map(|p| p.line()), e)?;
+                continue;
+            }
+        };
+        rows += 1;
+        let line = record.position().map(|p| p.line());
+        let before = report.count;
+        for check in &mut checks {
+            for msg in check.check(
+                record.get(check.index).unwrap_or_default(),
+                line.unwrap_or_default(),
+            ) {
+                report.violation(line, check.name, &msg)?;
+            }
+        }
+        if report.count > before {
+            invalid_rows += 1;
+        }
+    }
+    errors.finish()?;
+
+    if report.count == 0 {
+        writeln!(report.writer, "ok: {} rows, no violations", rows)?;
+    } else {
+        writeln!(
+            report.writer,
+            "{} violations in {} of {} rows",
+            report.count, invalid_rows, rows
+        )?;
+    }
+    report.writer.flush()?;
+    Ok(report.count)
+}
+
+struct Report<'a> {
+    writer: &'a mut dyn Write,
+    count: usize,
+}
+
+impl Report<'_> {
+    /// 输出一条违规，表头相关的问题没有行号
+    fn violation(&mut self, line: Option<u64>, column: &str, msg: &str) -> Result<()> {
+        self.count += 1;
+        match line {
+            Some(line) => writeln!(self.writer, "line {}, column {:?}: {}", line, column, msg)?,
+            None => writeln!(self.writer, "header, column {:?}: {}", column, msg)?,
+        }
+        Ok(())
+    }
+}
+
+///
+/// 校验规则，列按规则文件中的顺序保存
+///
+#[derive(Debug, Default)]
+struct Schema {
+    columns: Vec<(String, ColumnRule)>,
+    strict: bool,
+}
+
+/// YAML 规则文件的顶层结构
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    columns: Map<String, Value>,
+    #[serde(default)]
+    strict: bool,
+}
+
+///
+/// 单列的规则
+///
+/// - required：列必须存在，默认 true
+/// - nullable：允许空单元格，默认 true；空单元格不做其余检查
+/// - type 复用 --types 的类型：string, int, float, bool, datetime
+///
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnRule {
+    #[serde(rename = "type")]
+    ty: Option<ColumnType>,
+    #[serde(default = "default_true")]
+    required: bool,
+    #[serde(default = "default_true")]
+    nullable: bool,
+    #[serde(default)]
+    unique: bool,
+    pattern: Option<String>,
+    #[serde(rename = "enum")]
+    values: Option<Vec<Value>>,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// JSON Schema 中只起说明作用、不影响校验的关键字
+const ANNOTATIONS: &[&str] = &[
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+];
+
+/// 存在不支持的关键字时返回错误，避免规则被静默忽略
+fn check_keywords(doc: &Value, supported: &[&str]) -> Result<()> {
+    let Some(doc) = doc.as_object() else {
+        return Ok(());
+    };
+    for key in doc.keys() {
+        if !supported.contains(&key.as_str()) && !ANNOTATIONS.contains(&key.as_str()) {
+            anyhow::bail!("unsupported keyword {:?}", key);
+        }
+    }
+    Ok(())
+}
+
+impl Schema {
+    /// JSON 是 YAML 的子集，统一用 serde_yaml 读取，有 properties 时按 JSON Schema 解释
+    fn load(path: &str) -> Result<Self> {
+        let doc: Value =
+            serde_yaml::from_str(&fs::read_to_string(path)?).with_context(|| path.to_string())?;
+        if doc.get("properties").is_some() {
+            return Self::from_json_schema(&doc).with_context(|| path.to_string());
+        }
+        let spec: Spec = serde_json::from_value(doc).with_context(|| path.to_string())?;
+        let columns = spec
+            .columns
+            .into_iter()
+            .map(|(name, rule)| {
+                let rule = serde_json::from_value(rule)
+                    .with_context(|| format!("{}: column {:?}", path, name))?;
+                Ok((name, rule))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            columns,
+            strict: spec.strict,
+        })
+    }
+
+    fn from_json_schema(doc: &Value) -> Result<Self> {
+        check_keywords(
+            doc,
+            &["type", "properties", "required", "additionalProperties"],
+        )?;
+        if !matches!(doc["additionalProperties"], Value::Null | Value::Bool(_)) {
+            anyhow::bail!("additionalProperties must be a boolean");
+        }
+        let required: Vec<&str> = doc["required"]
+            .as_array()
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let properties = doc["properties"]
+            .as_object()
+            .ok_or_else(|| anyhow::anyhow!("properties must be an object"))?;
+        let mut columns = Vec::with_capacity(properties.len());
+        for (name, prop) in properties {
+            let rule = ColumnRule::from_json_schema(prop, required.contains(&name.as_str()))
+                .with_context(|| format!("property {:?}", name))?;
+            columns.push((name.clone(), rule));
+        }
+        // 只在 required 中出现的列同样要求存在
+        for name in required.iter().filter(|r| !properties.contains_key(**r)) {
+            columns.push((
+                name.to_string(),
+                ColumnRule::from_json_schema(&Value::Null, true)?,
+            ));
+        }
+        Ok(Self {
+            columns,
+            strict: doc["additionalProperties"] == Value::Bool(false),
+        })
+    }
+}
+
+impl ColumnRule {
+    /// required 中的列必须存在且不能为空，除非 type 中包含 "null"
+    fn from_json_schema(prop: &Value, required: bool) -> Result<Self> {
+        check_keywords(
+            prop,
+            &[
+                "type", "format", "pattern", "enum", "minimum", "maximum", "unique",
+            ],
+        )?;
+        let format = prop["format"].as_str();
+        let types: Vec<&str> = match &prop["type"] {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        let nullable = !required || types.contains(&"null");
+        let ty = match types.iter().find(|t| **t != "null") {
+            None => None,
+            Some(&"integer") => Some(ColumnType::Int),
+            Some(&"number") => Some(ColumnType::Float),
+            Some(&"boolean") => Some(ColumnType::Bool),
+            Some(&"string") => match format {
+                Some("date-time" | "date") => Some(ColumnType::Datetime),
+                _ => None,
+            },
+            Some(why) => anyhow::bail!("unsupported type {:?}", why),
+        };
+        if let Some(format) = format {
+            if !matches!(ty, Some(ColumnType::Datetime)) {
+                anyhow::bail!("unsupported format {:?}", format);
+            }
+        }
+        Ok(Self {
+            ty,
+            required,
+            nullable,
+            unique: prop["unique"] == Value::Bool(true),
+            pattern: prop["pattern"].as_str().map(|p| p.to_string()),
+            values: prop["enum"].as_array().cloned(),
+            min: prop["minimum"].as_f64(),
+            max: prop["maximum"].as_f64(),
+        })
+    }
+}
+
+///
+/// 一列的检查状态：编译后的正则以及 unique 检查用的已出现值
+///
+struct Check<'a> {
+    index: usize,
+    name: &'a str,
+    rule: &'a ColumnRule,
+    pattern: Option<Regex>,
+    values: Option<Vec<String>>,
+    seen: HashMap<String, u64>,
+}
+
+impl<'a> Check<'a> {
+    fn new(index: usize, name: &'a str, rule: &'a ColumnRule) -> Result<Self> {
+        let pattern = rule
+            .pattern
+            .as_deref()
+            .map(|p| Regex::new(p).with_context(|| format!("column {:?}: invalid pattern", name)))
+            .transpose()?;
+        let values = rule
+            .values
+            .as_ref()
+            .map(|values| values.iter().map(value_to_field).collect());
+        Ok(Self {
+            index,
+            name,
+            rule,
+            pattern,
+            values,
+            seen: HashMap::new(),
+        })
+    }
+
+    /// 返回该单元格违反的全部规则
+    fn check(&mut self, field: &str, line: u64) -> Vec<String> {
+        let mut violations = Vec::new();
+        if field.is_empty() {
+            if !self.rule.nullable {
+                violations.push("empty value".to_string());
+            }
+            return violations;
+        }
+        if let Some(ty) = self.rule.ty {
+            if let Err(e) = convert_value(field, ty) {
+                violations.push(e.to_string());
+            }
+        }
+        if self.rule.min.is_some() || self.rule.max.is_some() {
+            match field.trim().parse::<f64>() {
+                Ok(n) => {
+                    if let Some(min) = self.rule.min.filter(|min| n < *min) {
+                        violations.push(format!("{} is less than minimum {}", field, min));
+                    }
+                    if let Some(max) = self.rule.max.filter(|max| n > *max) {
+                        violations.push(format!("{} is greater than maximum {}", field, max));
+                    }
+                }
+                // 类型检查已经报告过时不再重复
+                Err(_) if violations.is_empty() => {
+                    violations.push(format!("{:?} is not a number", field))
+                }
+                Err(_) => {}
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(field) {
+                violations.push(format!(
+                    "{:?} does not match pattern {:?}",
+                    field,
+                    pattern.as_str()
+                ));
+            }
+        }
+        if let Some(values) = &self.values {
+            if !values.iter().any(|v| v == field) {
+                violations.push(format!("{:?} is not one of {:?}", field, values));
+            }
+        }
+        if self.rule.unique {
+            if let Some(first) = self.seen.get(field) {
+                violations.push(format!(
+                    "duplicate value {:?} (first seen at line {})",
+                    field, first
+                ));
+            } else {
+                self.seen.insert(field.to_string(), line);
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_validate() -> Result<()> {
+        let mut buf = Vec::new();
+        let count = process_csv_validate(
+            "fixtures/validate.csv",
+            "fixtures/validate_schema.yaml",
+            &CsvReadOpts::default(),
+            &mut buf,
+        )?;
+        let report = String::from_utf8(buf)?;
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            [
+                r#"line 3, column "email": "bob-at-example.com" does not match pattern "^[^@\\s]+@[^@\\s]+$""#,
+                r#"line 3, column "age": -1 is less than minimum 0"#,
+                r#"line 4, column "id": duplicate value "2" (first seen at line 3)"#,
+                r#"line 4, column "status": "pending" is not one of ["active", "inactive"]"#,
+                r#"line 5, column "email": empty value"#,
+                r#"line 5, column "age": cannot parse "abc" as int"#,
+                "6 violations in 3 of 4 rows",
+            ]
+        );
+        assert_eq!(count, 6);
+
+        let mut buf = Vec::new();
+        let count = process_csv_validate(
+            "fixtures/validate.csv",
+            "fixtures/validate_schema.json",
+            &CsvReadOpts::default(),
+            &mut buf,
+        )?;
+        let report = String::from_utf8(buf)?;
+        assert!(report.starts_with("header, column \"phone\": required column is missing\n"));
+        assert!(report.contains("line 5, column \"email\": empty value\n"));
+        assert!(report.contains("line 5, column \"age\": cannot parse \"abc\" as int\n"));
+        assert_eq!(count, 6);
+
+        let mut buf = Vec::new();
+        let count = process_csv_validate(
+            "fixtures/validate.csv",
+            "fixtures/diff_old.csv",
+            &CsvReadOpts::default(),
+            &mut buf,
+        );
+        assert!(count.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_schema_unsupported_keywords() -> Result<()> {
+        let schema = |prop: Value| {
+            Schema::from_json_schema(&serde_json::json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "properties": { "a": prop },
+            }))
+        };
+        assert!(schema(serde_json::json!({
+            "type": "string",
+            "format": "date",
+            "title": "A",
+            "description": "a date",
+        }))
+        .is_ok());
+        for prop in [
+            serde_json::json!({ "type": "string", "minLength": 1 }),
+            serde_json::json!({ "type": "string", "format": "email" }),
+            serde_json::json!({ "type": "integer", "format": "date" }),
+            serde_json::json!({ "type": "number", "exclusiveMinimum": 0 }),
+            serde_json::json!({ "type": "object" }),
+        ] {
+            assert!(schema(prop.clone()).is_err(), "{}", prop);
+        }
+        assert!(Schema::from_json_schema(&serde_json::json!({
+            "properties": {},
+            "patternProperties": { "^x": {} },
+        }))
+        .is_err());
+        assert!(Schema::from_json_schema(&serde_json::json!({
+            "properties": {},
+            "additionalProperties": { "type": "string" },
+        }))
+        .is_err());
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
-use std::{net::SocketAddr, path::PathBuf, sync::Arc};
-use axum::{extract::{Path, State}, http::StatusCode, routing::get, Router};
 use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Router,
+};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 use tokio::net::TcpListener;
 use tower_http::services::ServeDir;
 use tracing::{info, warn};
@@ -25,17 +30,20 @@ pub async fn process_http_serve(path: PathBuf, port: u16) -> Result<()> {
 }
 
 async fn file_handler(
-    State(state): State<Arc<HttpServeState>>, 
+    State(state): State<Arc<HttpServeState>>,
     Path(path): Path<String>,
 ) -> (StatusCode, String) {
     let p = std::path::Path::new(&state.path).join(path);
     info!("Reading file {:?}", p);
     if !p.exists() {
-        (StatusCode::NOT_FOUND, format!("File {} not found!", p.display()))
+        (
+            StatusCode::NOT_FOUND,
+            format!("File {} not found!", p.display()),
+        )
     } else {
         if p.is_dir() {
             let out_path = p.clone().join("index.html");
-            // 如果是目录，返回目录内容列表 
+            // 如果是目录，返回目录内容列表
             // 目录下所有文件输出到新建的index.html文件中， 格式 <html><body><ul>...</ul></body></html>
             // 使用 tokio 读取目录下所有文件
             let mut files: Vec<String> = Vec::new();
@@ -45,7 +53,6 @@ async fn file_handler(
                 let path = entry.path();
                 let file_name = path.file_name().unwrap().to_str().unwrap();
                 files.push(file_name.to_string());
-                
             }
             // 按照文件名排序， 每一条格式 <li><a href="path">path</a></li>
             files.sort();
@@ -64,27 +71,28 @@ async fn file_handler(
                 Ok(content) => {
                     info!("Read {} bytes", content.len());
                     (StatusCode::OK, content)
-                },
+                }
                 Err(err) => {
                     warn!("Error reading file {}", err);
                     (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
-                },
+                }
             }
         }
     }
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[tokio::test]
     async fn test_file_handler() {
-        let state = HttpServeState { path: PathBuf::from(".") };
-        let (status, content) = file_handler(State(Arc::new(state)), Path("fixtures".to_string())).await;
+        let state = HttpServeState {
+            path: PathBuf::from("."),
+        };
+        let (status, content) =
+            file_handler(State(Arc::new(state)), Path("Cargo.toml".to_string())).await;
         assert_eq!(status, StatusCode::OK);
         assert!(content.contains("[package]"));
-
     }
 }
@@ -1,11 +1,45 @@
 mod b64;
+mod csv_agg;
+mod csv_cat;
 mod csv_convert;
+mod csv_diff;
+mod csv_encoding;
+mod csv_errors;
+mod csv_filter;
+mod csv_join;
+mod csv_mask;
+mod csv_output;
+mod csv_query;
+mod csv_reverse;
+mod csv_sample;
+mod csv_show;
+mod csv_sort;
+mod csv_split;
+mod csv_sql;
+mod csv_stats;
+mod csv_types;
+mod csv_validate;
 mod gen_pass;
 mod http_serve;
 mod text;
 
 pub use b64::{process_decode, process_encode};
+pub use csv_agg::process_csv_agg;
+pub use csv_cat::process_csv_cat;
 pub use csv_convert::process_csv;
+pub use csv_diff::process_csv_diff;
+pub use csv_join::process_csv_join;
+pub use csv_query::process_csv_query;
+pub use csv_reverse::process_to_csv;
+pub use csv_sample::process_csv_sample;
+pub use csv_show::process_csv_show;
+pub use csv_sort::process_csv_sort;
+pub use csv_split::process_csv_split;
+pub use csv_stats::process_csv_stats;
+pub use csv_validate::process_csv_validate;
 pub use gen_pass::process_gen_pass;
 pub use http_serve::process_http_serve;
-pub use text::{process_text_keygen, process_text_sign, process_text_verify};
+pub use text::{
+    process_text_decrypt, process_text_encrypt, process_text_keygen, process_text_sign,
+    process_text_verify,
+};
@@ -3,22 +3,37 @@ use std::{fs, io::Read, path::Path};
 use crate::{get_reader, process::process_gen_pass, TextSignFormat};
 use anyhow::{Ok, Result};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
-use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Nonce,
+};
+use ed25519_dalek::{Digest, Sha512, Signature, SigningKey, VerifyingKey};
 use rand::rngs::OsRng as RandOsRng;
-// use chacha20poly1305::{
-//     aead::{Aead, AeadCore, KeyInit, OsRng},
-//     ChaCha20Poly1305, Nonce
-// };
+
+/// ChaCha20-Poly1305 nonce 长度（字节）
+const NONCE_LEN: usize = 12;
+
+/// 签名/验签时流式读取的块大小（字节）
+const CHUNK_SIZE: usize = 64 * 1024;
 
 trait TextSign {
-    // Sign the text from the reader and return the signature
+    // Sign the text streamed from the reader and return the signature
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
 }
 
 trait TextVerify {
-    // Verify the text from the reader with the given signature
-    // fn verify<R: Read>(&self, reader: R, signature: &[u8]) -> Result<bool>;
-    fn verify(&self, reader: impl Read, signature: &[u8]) -> Result<bool>;
+    // Verify the text streamed from the reader with the given signature
+    fn verify(&self, reader: &mut dyn Read, signature: &[u8]) -> Result<bool>;
+}
+
+trait TextEncrypt {
+    // Encrypt the text from the reader and return nonce + ciphertext
+    fn encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
+}
+
+trait TextDecrypt {
+    // Decrypt the nonce + ciphertext envelope and return the plaintext
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>>;
 }
 
 trait KeyLoader {
@@ -43,37 +58,44 @@ struct Ed22519Verifier {
     key: VerifyingKey,
 }
 
+struct ChaCha20 {
+    key: [u8; 32],
+}
+
 pub fn process_text_sign(input: &str, key: &str, format: TextSignFormat) -> Result<String> {
     let mut reader = get_reader(input)?;
-    let mut buf = Vec::new();
-    reader.read_to_end(&mut buf)?;
     let signed = match format {
         TextSignFormat::Blake3 => {
             let signer = Blake3::load(key)?;
             signer.sign(&mut reader)?
-        },
+        }
         TextSignFormat::Ed22519 => {
             let signer = Ed22519Signer::load(key)?;
             signer.sign(&mut reader)?
-        },
+        }
     };
     let signed = URL_SAFE_NO_PAD.encode(signed);
-    
+
     Ok(signed)
 }
 
-pub fn process_text_verify(input: &str, key: &str, sig: &str, format: TextSignFormat) -> Result<bool> {
+pub fn process_text_verify(
+    input: &str,
+    key: &str,
+    sig: &str,
+    format: TextSignFormat,
+) -> Result<bool> {
     let mut reader = get_reader(input)?;
     let sig = URL_SAFE_NO_PAD.decode(sig)?;
     let verified = match format {
         TextSignFormat::Blake3 => {
             let verifier = Blake3::load(key)?;
             verifier.verify(&mut reader, &sig)?
-        },
+        }
         TextSignFormat::Ed22519 => {
             let verifier = Ed22519Verifier::load(key)?;
             verifier.verify(&mut reader, &sig)?
-        },
+        }
     };
     Ok(verified)
 }
@@ -85,54 +107,139 @@ pub fn process_text_keygen(format: TextSignFormat) -> Result<Vec<Vec<u8>>> {
     }
 }
 
-// pub fn process_text_encrypt(input: &str) -> Result<String> {
-//     let mut reader = get_reader(input)?;
-//     let mut buf = String::new();
-//     reader.read_to_string(&mut buf)?;
-//     let buf = buf.trim();
-//     // 需要加密的 message
-//     let message = String::from_utf8(buf.as_bytes().to_vec())?;
+///
+/// 使用 ChaCha20-Poly1305 加密输入内容
+///
+/// return: URL-safe base64 编码的 nonce + ciphertext
+///
+pub fn process_text_encrypt(input: &str, key: &str) -> Result<String> {
+    let mut reader = get_reader(input)?;
+    let cipher = ChaCha20::load(key)?;
+    let encrypted = cipher.encrypt(&mut reader)?;
+    Ok(URL_SAFE_NO_PAD.encode(encrypted))
+}
+
+///
+/// 解密 process_text_encrypt 生成的 base64 内容，密文被篡改时返回错误
+///
+pub fn process_text_decrypt(input: &str, key: &str) -> Result<String> {
+    let mut reader = get_reader(input)?;
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let data = URL_SAFE_NO_PAD.decode(buf.trim())?;
+    let cipher = ChaCha20::load(key)?;
+    let decrypted = cipher.decrypt(&data)?;
+    Ok(String::from_utf8(decrypted)?)
+}
+
+///
+/// 按 CHUNK_SIZE 分块读取 reader，每读到一块调用一次 f，reader 只会被消费一次
+///
+fn read_chunks(reader: &mut dyn Read, mut f: impl FnMut(&[u8])) -> Result<()> {
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        f(&buf[..n]);
+    }
+    Ok(())
+}
+
+impl Blake3 {
+    fn hash(&self, reader: &mut dyn Read) -> Result<blake3::Hash> {
+        let mut hasher = blake3::Hasher::new_keyed(&self.key);
+        read_chunks(reader, |chunk| {
+            hasher.update(chunk);
+        })?;
+        Ok(hasher.finalize())
+    }
+
+    /// 内存中的短数据直接计算，不需要分配读取缓冲区
+    fn keyed_hash(&self, data: &[u8]) -> blake3::Hash {
+        blake3::keyed_hash(&self.key, data)
+    }
+}
+
+///
+/// 带密钥的 BLAKE3 哈希，供 csv 的 hash 脱敏逐个单元格计算摘要
+///
+pub(crate) struct KeyedHasher(Blake3);
 
-//     let key = ChaCha20Poly1305::generate_key(&mut OsRng);
-//     let cipher = ChaCha20Poly1305::new(&key);
-//     let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
-//     let ciphertext = cipher.encrypt(&nonce, message.as_ref())?;
-//     Ok()
-// }
+impl KeyedHasher {
+    pub(crate) fn load(key: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self(Blake3::load(key)?))
+    }
 
+    pub(crate) fn hash(&self, data: &[u8]) -> blake3::Hash {
+        self.0.keyed_hash(data)
+    }
+}
+
+/// Ed25519ph 使用 SHA-512 对消息做预哈希
+fn prehash(reader: &mut dyn Read) -> Result<Sha512> {
+    let mut hasher = Sha512::new();
+    read_chunks(reader, |chunk| hasher.update(chunk))?;
+    Ok(hasher)
+}
 
 impl TextSign for Blake3 {
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-        Ok(blake3::keyed_hash(&self.key, &buf).as_bytes().to_vec())
+        Ok(self.hash(reader)?.as_bytes().to_vec())
     }
 }
 
 impl TextVerify for Blake3 {
-    fn verify(&self, mut reader: impl Read, sig: &[u8]) -> Result<bool> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-        let hash = blake3::keyed_hash(&self.key, &buf);
-        let hash = hash.as_bytes();
-        Ok(hash == sig)
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let sig: [u8; 32] = sig.try_into()?;
+        let hash = self.hash(reader)?;
+        // blake3::Hash 的比较是常数时间的，避免通过时间差猜测 MAC
+        Ok(hash == blake3::Hash::from(sig))
     }
 }
 
 impl TextSign for Ed22519Signer {
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-        Ok(self.key.sign(&buf).to_bytes().to_vec())
+        let hasher = prehash(reader)?;
+        let sig = self.key.sign_prehashed(hasher, None)?;
+        Ok(sig.to_bytes().to_vec())
     }
 }
 
 impl TextVerify for Ed22519Verifier {
-    fn verify(&self, mut reader: impl Read, sig: &[u8]) -> Result<bool> {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        let sig = Signature::from_bytes(sig.try_into()?);
+        let hasher = prehash(reader)?;
+        Ok(self.key.verify_prehashed(hasher, None, &sig).is_ok())
+    }
+}
+
+impl TextEncrypt for ChaCha20 {
+    fn encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
-        let sig = Signature::from_bytes(sig.try_into()?);
-        Ok(self.key.verify(&buf, &sig).is_ok())
+        let cipher = ChaCha20Poly1305::new(&self.key.into());
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, buf.as_ref())
+            .map_err(|e| anyhow::anyhow!("Encrypt failed: {}", e))?;
+        let mut ret = nonce.to_vec();
+        ret.extend(ciphertext);
+        Ok(ret)
+    }
+}
+
+impl TextDecrypt for ChaCha20 {
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("Invalid ciphertext: too short"));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let cipher = ChaCha20Poly1305::new(&self.key.into());
+        cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Decrypt failed: wrong key or tampered ciphertext"))
     }
 }
 
@@ -142,8 +249,13 @@ impl Blake3 {
     }
 
     fn try_new(key: &[u8]) -> Result<Self> {
-        let key = &key[..32];
-        let key: [u8; 32] = key.try_into()?;
+        if key.len() < 32 {
+            return Err(anyhow::anyhow!(
+                "Invalid key: expect 32 bytes, got {}",
+                key.len()
+            ));
+        }
+        let key: [u8; 32] = key[..32].try_into()?;
         let signer = Blake3::new(key);
         Ok(signer)
     }
@@ -173,6 +285,23 @@ impl Ed22519Verifier {
     }
 }
 
+impl ChaCha20 {
+    fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    fn try_new(key: &[u8]) -> Result<Self> {
+        if key.len() < 32 {
+            return Err(anyhow::anyhow!(
+                "Invalid key: expect 32 bytes, got {}",
+                key.len()
+            ));
+        }
+        let key: [u8; 32] = key[..32].try_into()?;
+        Ok(ChaCha20::new(key))
+    }
+}
+
 impl KeyLoader for Blake3 {
     fn load(key: impl AsRef<Path>) -> Result<Self> {
         let key = fs::read(key)?;
@@ -194,10 +323,16 @@ impl KeyLoader for Ed22519Verifier {
     }
 }
 
+impl KeyLoader for ChaCha20 {
+    fn load(key: impl AsRef<Path>) -> Result<Self> {
+        let key = fs::read(key)?;
+        Self::try_new(&key)
+    }
+}
+
 impl KeyGenerater for Blake3 {
     fn generate() -> Result<Vec<Vec<u8>>> {
-        let key = process_gen_pass(32, true, 
-            true, true, true)?;
+        let key = process_gen_pass(32, true, true, true, true)?;
         let vec = key.as_bytes().to_vec();
         Ok(vec![vec])
     }
@@ -219,29 +354,91 @@ mod tests {
 
     ///
     /// 运行用例前先使用 generate 工具生成对应的key文件
-    /// 
+    ///
     #[test]
     fn test_blake3_sign_verify() -> Result<()> {
         let blake3 = Blake3::load("fixtures/blake3.txt")?;
         let data = b"Hello, world!";
         let sig = blake3.sign(&mut &data[..])?;
-        let verified = blake3.verify(&data[..], &sig)?;
+        let verified = blake3.verify(&mut &data[..], &sig)?;
         assert!(verified);
+        assert!(!blake3.verify(&mut &b"Hello, world?"[..], &sig)?);
+        assert!(blake3.verify(&mut &data[..], &sig[..31]).is_err());
+        assert_eq!(blake3.keyed_hash(data).as_bytes(), &sig[..]);
         Ok(())
     }
 
-    /// 
+    ///
     /// 运行用例前先使用 generate 工具生成对应的sk和pk文件
-    /// 
+    ///
     #[test]
     fn test_ed22519_sign_verify() -> Result<()> {
         let signer = Ed22519Signer::load("fixtures/ed25519.sk")?;
         let verifier = Ed22519Verifier::load("fixtures/ed25519.pk")?;
         let data = b"Hello, world!";
         let sig = signer.sign(&mut &data[..])?;
-        let verified = verifier.verify(&data[..], &sig)?;
+        let verified = verifier.verify(&mut &data[..], &sig)?;
+        assert!(verified);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blake3_sign_streams_multiple_chunks() -> Result<()> {
+        let blake3 = Blake3::load("fixtures/blake3.txt")?;
+        let data = vec![7u8; CHUNK_SIZE * 3 + 17];
+        let sig = blake3.sign(&mut &data[..])?;
+        assert_eq!(sig, blake3::keyed_hash(&blake3.key, &data).as_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ed22519_verify_rejects_modified_message() -> Result<()> {
+        let signer = Ed22519Signer::load("fixtures/ed25519.sk")?;
+        let verifier = Ed22519Verifier::load("fixtures/ed25519.pk")?;
+        let data = vec![1u8; CHUNK_SIZE + 1];
+        let sig = signer.sign(&mut &data[..])?;
+        let mut modified = data.clone();
+        modified[CHUNK_SIZE] = 2;
+        assert!(!verifier.verify(&mut &modified[..], &sig)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_reads_input() -> Result<()> {
+        let sig = process_text_sign(
+            "fixtures/base64.txt",
+            "fixtures/blake3.txt",
+            TextSignFormat::Blake3,
+        )?;
+        let verified = process_text_verify(
+            "fixtures/base64.txt",
+            "fixtures/blake3.txt",
+            &sig,
+            TextSignFormat::Blake3,
+        )?;
         assert!(verified);
+        let empty = Blake3::load("fixtures/blake3.txt")?.sign(&mut &b""[..])?;
+        assert_ne!(sig, URL_SAFE_NO_PAD.encode(empty));
         Ok(())
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_chacha20_encrypt_decrypt() -> Result<()> {
+        let cipher = ChaCha20::load("fixtures/blake3.txt")?;
+        let data = b"Hello, world!";
+        let encrypted = cipher.encrypt(&mut &data[..])?;
+        let decrypted = cipher.decrypt(&encrypted)?;
+        assert_eq!(decrypted, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chacha20_decrypt_tampered() -> Result<()> {
+        let cipher = ChaCha20::load("fixtures/blake3.txt")?;
+        let mut encrypted = cipher.encrypt(&mut &b"Hello, world!"[..])?;
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 0x01;
+        assert!(cipher.decrypt(&encrypted).is_err());
+        Ok(())
+    }
+}
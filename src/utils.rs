@@ -1,5 +1,12 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
 
-use std::{fs::File, io::Read, path::{Path, PathBuf}};
+/// 标准输出是否已被下游关闭（如 `rcli csv ... | head`）
+static STDOUT_CLOSED: AtomicBool = AtomicBool::new(false);
 
 ///
 /// 验证文件是否存在
@@ -28,7 +35,7 @@ pub fn verify_path(path: &str) -> Result<PathBuf, &'static str> {
     }
 }
 
-pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>>{
+pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>> {
     let reader: Box<dyn Read> = if input == "-" {
         Box::new(std::io::stdin())
     } else {
@@ -37,9 +44,54 @@ pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>>{
     Ok(reader)
 }
 
+///
+/// 获取输出，"-" 表示标准输出，否则创建（覆盖）对应文件
+///
+pub fn get_writer(output: &str) -> anyhow::Result<Box<dyn Write>> {
+    let writer: Box<dyn Write> = if output == "-" {
+        Box::new(BufWriter::new(PipeWriter(io::stdout())))
+    } else {
+        Box::new(BufWriter::new(File::create(output)?))
+    };
+    Ok(writer)
+}
+
+///
+/// 写标准输出时是否遇到过 BrokenPipe
+///
+/// 错误仍按正常路径返回（临时文件等得以清理），main 据此将其视为正常退出
+///
+pub fn stdout_closed() -> bool {
+    STDOUT_CLOSED.load(Ordering::Relaxed)
+}
+
+/// 记录 BrokenPipe 的标准输出包装
+struct PipeWriter<W>(W);
+
+impl<W: Write> PipeWriter<W> {
+    fn check<T>(result: io::Result<T>) -> io::Result<T> {
+        if matches!(&result, Err(e) if e.kind() == ErrorKind::BrokenPipe) {
+            STDOUT_CLOSED.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl<W: Write> Write for PipeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Self::check(self.0.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Self::check(self.0.flush())
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::{get_reader, verify_file};
+    use super::PipeWriter;
+    use crate::{get_reader, get_writer, stdout_closed, verify_file};
+    use std::io::{self, ErrorKind, Write};
 
     #[test]
     fn test_verify_file() {
@@ -58,4 +110,34 @@ mod test {
         println!("{}", buf);
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_get_writer() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli_test_get_writer.txt");
+        let output = output.to_str().unwrap();
+        let mut writer = get_writer(output)?;
+        writer.write_all(b"hello")?;
+        drop(writer);
+        assert_eq!(std::fs::read_to_string(output)?, "hello");
+        std::fs::remove_file(output)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipe_writer_records_broken_pipe() {
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut writer = PipeWriter(Closed);
+        assert!(writer.flush().is_ok());
+        assert!(!stdout_closed());
+        assert!(writer.write_all(b"hello").is_err());
+        assert!(stdout_closed());
+    }
+}